@@ -1,37 +1,7 @@
-if (-not (Test-Path -Path "$PSScriptRoot\dirs.txt"))
-{
-    "$env:userprofile\projects" | Out-File -FilePath "$PSScriptRoot\dirs.txt" -Encoding utf8
-}
-
-if ($args.Count -eq 0)
-{
-    $res = & sugar
-    if ($res.Count -eq 0)
-    {
-        exit 0
-    }
-
-    foreach ($r in $res)
-    {
-        if ($r -ne "")
-        {
-            $selectedPath = $r
-            break
-        }
-    }
-
-    $header = ($selectedPath -split " ")[0]
-    if ($header -ne "`e[33m[WARN]`e[0m" -and $header -ne "`e[31m[ERROR]`e[0m")
-    {
-        Set-Location -Path $selectedPath
-        "Changed Directory to ""$selectedPath"""
-    } else
-    {
-        Write-Host $selectedPath
-    }
-
-} else
-{
-    & sugar $args
-}
+# the sgr function comes from `sugar init pwsh`, so it stays in step with
+# sugar itself. Once defined it takes over from this script for the rest of
+# the session; put the line below in $PROFILE to skip this script altogether
+Invoke-Expression (& sugar init pwsh | Out-String)
 
+sgr @args
+exit $LASTEXITCODE
@@ -0,0 +1,206 @@
+// standard library
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// own
+use crate::config;
+use crate::discover::Repo;
+use crate::flags::Flags;
+use crate::key::Key;
+use crate::utils::{format_log, shell_command, shell_quote, Failure, LogLevel};
+
+/// what is done with the repos picked in `run` or found by `query`
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    /// print the paths for the shell function from `init` to cd to
+    Cd,
+    /// print the paths like `Cd`, but to stderr for the shell function to
+    /// show instead of cd'ing to them
+    Print,
+    /// open them in $VISUAL or $EDITOR
+    Editor,
+    /// open them in VS Code
+    Code,
+    /// start $SHELL in each of them
+    Shell,
+    /// run `action-command` in config.txt for each of them
+    Command,
+}
+
+/// keys that pick a repo with another action than Enter does, unless
+/// config.txt says otherwise; the one for `command` only counts along with
+/// `action-command`
+pub const DEFAULT_KEYS: &str = "ctrl-o:editor,ctrl-v:code,ctrl-t:shell,ctrl-x:command,ctrl-y:print";
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::Cd,
+        Action::Print,
+        Action::Editor,
+        Action::Code,
+        Action::Shell,
+        Action::Command,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Cd => "cd",
+            Action::Print => "print",
+            Action::Editor => "editor",
+            Action::Code => "code",
+            Action::Shell => "shell",
+            Action::Command => "command",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Action, String> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == name)
+            .ok_or(format_log(
+                LogLevel::Error,
+                format!(
+                    "unknown action '{}'; expected cd, print, editor, code, shell or command",
+                    name
+                ),
+            ))
+    }
+
+    /// `key:action` pairs separated by commas, like `ctrl-o:editor`
+    pub fn parse_keys(list: &str) -> Result<Vec<(Key, Action)>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, action) = pair.split_once(':').ok_or(format_log(
+                    LogLevel::Error,
+                    format!(
+                        "'action-keys' in config.txt expects key:action pairs; got '{}'",
+                        pair
+                    ),
+                ))?;
+                Ok((
+                    Key::parse("'action-keys' in config.txt", key.trim())?,
+                    Action::parse(action.trim())?,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// $VISUAL or $EDITOR unless they are empty, or what every system has
+/// otherwise
+pub fn editor() -> String {
+    #[cfg(windows)]
+    let fallback = "notepad";
+    #[cfg(not(windows))]
+    let fallback = "vi";
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or(fallback.to_string())
+}
+
+/// Does `action` with `repos`. Only `Cd` and `Print` leave anything for
+/// stdout, which is `repos` as `output` makes them. The shell function from
+/// `init` sets `SGR_CD` to cd to stdout, so then `Print` prints it to stderr
+/// instead.
+pub fn run<F>(action: Action, repos: &[Repo], flags: &Flags, output: F) -> Result<String, Failure>
+where
+    F: FnOnce(&[Repo], &Flags) -> String,
+{
+    let paths: Vec<String> = repos
+        .iter()
+        .map(|repo| repo.path.display().to_string())
+        .collect();
+    let paths = paths.as_slice();
+    match action {
+        Action::Cd => return Ok(output(repos, flags)),
+        Action::Print if std::env::var_os("SGR_CD").is_some_and(|cd| !cd.is_empty()) => {
+            let printed = output(repos, flags);
+            match flags.print0 {
+                true => eprint!("{}", printed),
+                false => eprintln!("{}", printed),
+            }
+        }
+        Action::Print => return Ok(output(repos, flags)),
+        // an editor can take several files, or directories here
+        Action::Editor => edit(paths)?,
+        Action::Code => {
+            let mut command = shell_command(&format!("code {}", quote_all(paths)));
+            wait(&mut command, "code")?;
+        }
+        Action::Shell => {
+            #[cfg(windows)]
+            let shell = std::env::var("COMSPEC").unwrap_or("cmd".to_string());
+            #[cfg(not(windows))]
+            let shell = std::env::var("SHELL").unwrap_or("sh".to_string());
+            for path in paths {
+                let mut command = Command::new(&shell);
+                command.current_dir(path);
+                wait(&mut command, &shell)?;
+            }
+        }
+        Action::Command => {
+            let settings = config::read_settings(flags)?;
+            let template = settings.action_command.ok_or(format_log(
+                LogLevel::Error,
+                "the 'command' action needs 'action-command' in config.txt".to_string(),
+            ))?;
+            for path in paths {
+                let name = Path::new(path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut command = shell_command(
+                    &template
+                        .replace("{path}", &shell_quote(path))
+                        .replace("{name}", &shell_quote(&name)),
+                );
+                command.current_dir(path);
+                wait(&mut command, "action-command")?;
+            }
+        }
+    }
+    Ok(String::new())
+}
+
+/// Opens `paths` in `editor()` and waits for it to close. It goes through the
+/// shell, so an editor with args like `code --wait` works.
+pub fn edit(paths: &[String]) -> Result<(), String> {
+    let editor = editor();
+    let mut command = shell_command(&format!("{} {}", editor, quote_all(paths)));
+    wait(&mut command, &editor)
+}
+
+fn quote_all(paths: &[String]) -> String {
+    paths
+        .iter()
+        .map(|path| shell_quote(path))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Runs `command` until it exits. Its stdout is the terminal, since sgr's own
+/// is read by the shell function from `init`.
+fn wait(command: &mut Command, name: &str) -> Result<(), String> {
+    #[cfg(windows)]
+    let tty = std::fs::OpenOptions::new().write(true).open("CONOUT$");
+    #[cfg(not(windows))]
+    let tty = std::fs::OpenOptions::new().write(true).open("/dev/tty");
+    if let Ok(tty) = tty {
+        command.stdout(Stdio::from(tty));
+    }
+
+    let status = command
+        .status()
+        .map_err(|e| format_log(LogLevel::Error, format!("failed to start {}: {}", name, e)))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format_log(
+            LogLevel::Error,
+            format!("{} failed: {}", name, status),
+        )),
+    }
+}
@@ -3,14 +3,13 @@ use std::fs::OpenOptions;
 use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
 
-// third party
-use walkdir::WalkDir;
-
 //own
-use crate::list;
-use crate::utils::{format_log, LogLevel, PathExt, StringExt};
+use crate::config::{self, Root};
+use crate::flags::Flags;
+use crate::utils::{format_log, Failure, LogLevel, PathExt, StringExt};
+use crate::{discover, list};
 
-pub fn execute(dir: Option<&str>) -> Result<(), String> {
+pub fn execute(dir: Option<&str>, flags: &Flags) -> Result<(), Failure> {
     // before
     list::execute(Some("all"), Some("dirs.txt: before add".to_string()))?;
 
@@ -34,27 +33,16 @@ pub fn execute(dir: Option<&str>) -> Result<(), String> {
 
     let trimmed_path = abs_path.strip_prefix(r#"\\?\"#).unwrap_or(&abs_path);
 
-    WalkDir::new(&trimmed_path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .find(|e| {
-            e.file_type().is_dir() && e.path().ends_with(".git") && e.path().join("HEAD").exists()
-        })
-        .ok_or(format_log(
+    let mut root = Root::new(trimmed_path.into());
+    root.apply(flags);
+    if !discover::contains_repo(&root, &config::read_settings(flags)?) {
+        return Err(Failure::from(format_log(
             LogLevel::Error,
-            format!("No git repos found in directory '{}'", trimmed_path),
-        ))?;
+            format!("No repos found in directory '{}'", trimmed_path),
+        )));
+    }
 
-    let dirs_txt_path = std::env::current_exe()
-        .map_err(|e| {
-            format_log(
-                LogLevel::Error,
-                format!("Failed to get sgr.exe path: {}", e),
-            )
-        })?
-        .parent()
-        .ok_or("Failed to get parent directory of sgr.exe")?
-        .join("dirs.txt");
+    let dirs_txt_path = config::dirs_txt_path()?;
 
     let mut file = OpenOptions::new()
         .read(true)
@@ -73,6 +61,10 @@ pub fn execute(dir: Option<&str>) -> Result<(), String> {
         .lines()
         .filter_map(|line| line.ok())
         .any(|line| {
+            let line = match Root::parse(&line) {
+                Ok(root) => root.path.display().to_string(),
+                Err(_) => line,
+            };
             let line_lowercase = line.trim().to_lowercase();
             let trimmed_path_lowercase = trimmed_path.to_lowercase();
 
@@ -108,14 +100,14 @@ pub fn execute(dir: Option<&str>) -> Result<(), String> {
             }
         })
     {
-        return Err(collision_msg);
+        return Err(Failure::from(collision_msg));
     }
 
     if let Err(e) = writeln!(file, "{}", trimmed_path) {
-        return Err(format_log(
+        return Err(Failure::from(format_log(
             LogLevel::Error,
             format!("Failed to write to file: {}", e),
-        ));
+        )));
     }
 
     // list updated dir
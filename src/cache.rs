@@ -0,0 +1,647 @@
+// standard library
+use std::collections::{HashMap, HashSet};
+use std::fs::{remove_file, rename, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+// own
+use crate::config::{self, Root, Settings};
+use crate::discover::{self, Repo, Report, Stamp};
+use crate::flags::Flags;
+use crate::preview;
+use crate::utils::{format_log, read_lines, Failure, LogLevel, StringExt};
+
+/// Bumped whenever the layout of cache.txt changes. A cache with another
+/// version is thrown away and rebuilt instead of being misread.
+const VERSION: &str = "sgr-cache 2";
+
+/// what the cache knows about one root
+struct Entry {
+    /// how the root was scanned; see `options`
+    options: String,
+    repos: Vec<Repo>,
+    stamps: Vec<Stamp>,
+}
+
+impl Entry {
+    /// nothing was added, removed or renamed in any directory the scan read,
+    /// and none of the ignore files it read were edited
+    fn is_fresh(&self) -> bool {
+        self.stamps.iter().all(|stamp| {
+            std::fs::metadata(&stamp.path)
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|mtime| mtime == stamp.mtime)
+        })
+    }
+}
+
+/// `discover::scan` backed by cache.txt.
+///
+/// Repos cached for a root are passed to `on_hit` right away. Then every
+/// cached root is checked for changes and the changed or uncached ones are
+/// scanned again, passing only repos not seen yet to `on_hit`. The cache is
+/// only written if that finishes, so stopping early leaves it as it was.
+pub fn scan<F>(roots: &[Root], settings: &Settings, report: &Report, on_hit: F) -> Vec<Repo>
+where
+    F: Fn(&Repo) -> bool + Sync,
+{
+    let cache_txt_path = cache_txt_path();
+    let cache_txt_path = cache_txt_path.as_deref().map_err(String::as_str);
+    scan_at(cache_txt_path, roots, settings, report, on_hit)
+}
+
+/// `scan` with the cache in `cache_txt_path`, or none if it is not known
+fn scan_at<F>(
+    cache_txt_path: Result<&Path, &str>,
+    roots: &[Root],
+    settings: &Settings,
+    report: &Report,
+    on_hit: F,
+) -> Vec<Repo>
+where
+    F: Fn(&Repo) -> bool + Sync,
+{
+    let mut cache = cache_txt_path.map(load_from).unwrap_or_default();
+    let options: Vec<String> = roots.iter().map(|root| options(root, settings)).collect();
+
+    // a repo can be both cached and found again by a rescan
+    let emitted = Mutex::new(HashSet::new());
+    let emit = |repo: &Repo| {
+        let first_time = emitted
+            .lock()
+            .is_ok_and(|mut emitted| emitted.insert(repo.path.clone()));
+        !first_time || on_hit(repo)
+    };
+
+    let mut repos = Vec::new();
+    let mut stale = Vec::new();
+    for (i, root) in roots.iter().enumerate() {
+        let entry = match cache.get(&root.path) {
+            Some(entry) if entry.options == options[i] => entry,
+            _ => {
+                stale.push(i);
+                continue;
+            }
+        };
+        // cached repos are not tied to a line of dirs.txt until now
+        let cached: Vec<Repo> = entry
+            .repos
+            .iter()
+            .map(|repo| Repo {
+                root: i,
+                ..repo.clone()
+            })
+            .collect();
+        for repo in cached.iter().filter(|repo| repo.path.exists()) {
+            if !emit(repo) {
+                return repos;
+            }
+        }
+        match entry.is_fresh() {
+            true => repos.extend(cached),
+            false => stale.push(i),
+        }
+    }
+    if stale.is_empty() {
+        return repos;
+    }
+
+    let stale_roots: Vec<Root> = stale.iter().map(|&i| roots[i].clone()).collect();
+    let scanned = discover::scan_stamped(&stale_roots, settings, report, |repo| {
+        emit(&Repo {
+            root: stale[repo.root],
+            ..repo.clone()
+        })
+    });
+    let (scanned, stamps) = match scanned {
+        Some(scanned) => scanned,
+        None => return repos,
+    };
+
+    for (j, &i) in stale.iter().enumerate() {
+        cache.insert(
+            roots[i].path.clone(),
+            Entry {
+                options: options[i].clone(),
+                repos: scanned
+                    .iter()
+                    .filter(|repo| repo.root == j)
+                    .cloned()
+                    .collect(),
+                stamps: stamps
+                    .iter()
+                    .filter(|stamp| stamp.root == j)
+                    .cloned()
+                    .collect(),
+            },
+        );
+    }
+    // drop roots that were removed from dirs.txt
+    cache.retain(|path, _| roots.iter().any(|root| &root.path == path));
+    // `save` errors already have their header
+    let saved = match cache_txt_path {
+        Ok(path) => save_to(path, &cache),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = saved {
+        eprintln!("{}", e);
+    }
+
+    repos.extend(scanned.into_iter().map(|repo| Repo {
+        root: stale[repo.root],
+        ..repo
+    }));
+    repos.sort_by(|a, b| a.root.cmp(&b.root).then_with(|| a.path.cmp(&b.path)));
+    repos
+}
+
+pub fn execute(arg: Option<&str>, flags: &Flags) -> Result<(), Failure> {
+    let arg = arg.ok_or(format_log(
+        LogLevel::Error,
+        "missing arg for 'cache'".to_string(),
+    ))?;
+
+    match arg {
+        "rebuild" => {
+            let roots = config::read_roots(flags)?;
+            let settings = config::read_settings(flags)?;
+            let report = Report::default();
+            let scanned = discover::scan_stamped(&roots, &settings, &report, |_| true);
+            report.print(&roots, &settings);
+            let (repos, stamps) = scanned.ok_or(format_log(
+                LogLevel::Error,
+                "search did not finish; cache.txt was left as it was".to_string(),
+            ))?;
+
+            let cache: HashMap<PathBuf, Entry> = roots
+                .iter()
+                .enumerate()
+                .map(|(i, root)| {
+                    let entry = Entry {
+                        options: options(root, &settings),
+                        repos: repos
+                            .iter()
+                            .filter(|repo| repo.root == i)
+                            .cloned()
+                            .collect(),
+                        stamps: stamps
+                            .iter()
+                            .filter(|stamp| stamp.root == i)
+                            .cloned()
+                            .collect(),
+                    };
+                    (root.path.clone(), entry)
+                })
+                .collect();
+            save(&cache)?;
+            println!(
+                "cached {} repos found in {} line(s) of dirs.txt",
+                repos.len(),
+                roots.len()
+            );
+        }
+        "clear" => {
+            let cache_txt_path = cache_txt_path()?;
+            match remove_file(&cache_txt_path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(Failure::from(format_log(
+                        LogLevel::Error,
+                        format!("Failed to remove file \"cache.txt\": {}", e),
+                    )))
+                }
+            }
+            preview::clear()?;
+            println!("cleared {}", cache_txt_path.display());
+        }
+        _ => {
+            return Err(Failure::from(format_log(
+                LogLevel::Error,
+                format!(
+                    "unknown arg '{}' for 'cache'; expected rebuild or clear",
+                    arg
+                ),
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn cache_txt_path() -> Result<PathBuf, String> {
+    Ok(config::config_dir()?.join("cache.txt"))
+}
+
+/// A root's results depend on how it was scanned, so everything that changes
+/// them is kept with its entry. Changing any of it is a cache miss. Ignore
+/// files under the root are stamped by the scan instead.
+fn options(root: &Root, settings: &Settings) -> String {
+    let mtime = |path: PathBuf| {
+        path.metadata()
+            .and_then(|meta| meta.modified())
+            .map(|mtime| format_time(&mtime))
+            .unwrap_or_default()
+    };
+    let ignore_txt_mtime = config::ignore_txt_path().map(mtime).unwrap_or_default();
+    // only read with `gitignore` on
+    let parent_ignores: Vec<String> = match settings.gitignore {
+        true => root
+            .path
+            .ancestors()
+            .skip(1)
+            .flat_map(|dir| {
+                discover::ignore_files(settings)
+                    .iter()
+                    .map(|name| dir.join(name))
+            })
+            .filter(|path| path.is_file())
+            .map(|path| format!("{}@{}", path.display(), mtime(path.clone())))
+            .collect(),
+        false => Vec::new(),
+    };
+    let kinds = match &settings.kinds {
+        Some(kinds) => kinds.iter().map(|kind| kind.name()).collect(),
+        None => vec!["all"],
+    };
+    let vcs: Vec<&str> = settings.detectors.iter().map(|d| d.name()).collect();
+
+    format!(
+        "nested={} depth={:?} follow={} one-file-system={} gitignore={} kinds={} vcs={} ignore.txt={} parents={}",
+        root.nested,
+        root.depth.or(settings.depth),
+        root.follow,
+        root.one_file_system || settings.one_file_system,
+        settings.gitignore,
+        kinds.join(","),
+        vcs.join(","),
+        ignore_txt_mtime,
+        parent_ignores.join(",")
+    )
+}
+
+/// Reads cache.txt:
+/// ```text
+/// sgr-cache 2
+/// root<TAB>options<TAB>path
+/// repo<TAB>vcs<TAB>kind<TAB>path
+/// stamp<TAB>mtime<TAB>path
+/// ```
+/// where `repo` and `stamp` lines belong to the `root` line above them, and
+/// a stamp is a directory or ignore file the scan read. A missing,
+/// unreadable or outdated cache is an empty one.
+fn load_from(path: &Path) -> HashMap<PathBuf, Entry> {
+    let mut cache = HashMap::new();
+    let file = match OpenOptions::new().read(true).open(path) {
+        Ok(file) => file,
+        Err(_) => return cache,
+    };
+
+    let mut lines = read_lines(BufReader::new(file));
+    if lines.next().as_deref() != Some(VERSION) {
+        return cache;
+    }
+
+    let mut current: Option<(PathBuf, Entry)> = None;
+    for line in lines {
+        let fields: Vec<&str> = line.splitn(4, '\t').collect();
+        if let ["root", options, path] = fields[..] {
+            let entry = Entry {
+                options: options.to_string(),
+                repos: Vec::new(),
+                stamps: Vec::new(),
+            };
+            if let Some((path, entry)) = current.replace((PathBuf::from(path), entry)) {
+                cache.insert(path, entry);
+            }
+            continue;
+        }
+
+        let entry = match current.as_mut() {
+            Some((_, entry)) => entry,
+            None => continue,
+        };
+        match fields[..] {
+            ["repo", vcs, kind, path] => {
+                entry.repos.extend(Repo::from_names(0, vcs, kind, path));
+            }
+            ["stamp", mtime, path] => {
+                if let Some(mtime) = parse_time(mtime) {
+                    entry.stamps.push(Stamp {
+                        root: 0,
+                        path: PathBuf::from(path),
+                        mtime,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((path, entry)) = current {
+        cache.insert(path, entry);
+    }
+    cache
+}
+
+/// written to a temp file first so a cut off write never leaves a broken cache
+fn save(cache: &HashMap<PathBuf, Entry>) -> Result<(), String> {
+    save_to(&cache_txt_path()?, cache)
+}
+
+fn save_to(cache_txt_path: &Path, cache: &HashMap<PathBuf, Entry>) -> Result<(), String> {
+    let temp_file_path = cache_txt_path.with_file_name("temp_cache.txt");
+    let temp_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&temp_file_path)
+        .map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"temp_cache.txt\": {}", e),
+            )
+        })?;
+
+    let mut writer = BufWriter::new(temp_file);
+    let mut write = || -> std::io::Result<()> {
+        writeln!(writer, "{}", VERSION)?;
+        for (path, entry) in cache {
+            writeln!(writer, "root\t{}\t{}", entry.options, path.display())?;
+            for repo in &entry.repos {
+                writeln!(
+                    writer,
+                    "repo\t{}\t{}\t{}",
+                    repo.vcs,
+                    repo.kind.name(),
+                    repo.path.display()
+                )?;
+            }
+            for stamp in &entry.stamps {
+                writeln!(
+                    writer,
+                    "stamp\t{}\t{}",
+                    format_time(&stamp.mtime),
+                    stamp.path.display()
+                )?;
+            }
+        }
+        writer.flush()
+    };
+    write().map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to write to \"temp_cache.txt\": {}", e),
+        )
+    })?;
+
+    rename(&temp_file_path, cache_txt_path).map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!(
+                "Failed to rename \"temp_cache.txt\" to \"cache.txt\": {}",
+                e
+            ),
+        )
+    })
+}
+
+/// `secs.nanos` since the unix epoch
+fn format_time(time: &SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{}", since_epoch.as_secs(), since_epoch.subsec_nanos())
+}
+
+fn parse_time(time: &str) -> Option<SystemTime> {
+    let (secs, nanos) = time.split_once('.')?;
+    let since_epoch = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+    Some(SystemTime::UNIX_EPOCH + since_epoch)
+}
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "cache"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "cache".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!("{}rebuilds or clears the cache of found repos", title);
+    println!(
+        "{}that lets sgr show results before searching\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!(
+            "{}sgr checks whether cached results are outdated while the picker is open",
+            "".to_string().pad_right(15).fill_left(2)
+        );
+        println!(
+            "{}and searches again only the directories in dirs.txt that changed",
+            "".to_string().pad_right(15).fill_left(2)
+        );
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!("{}", "sgr cache rebuild".to_string().fill_left(17));
+        println!("{}", "sgr cache clear".to_string().fill_left(17));
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. The cache is kept in cache.txt, next to dirs.txt");
+        println!("  2. Set \"cache = false\" in config.txt to always search from scratch");
+        println!("  3. \"clear\" also deletes the cached previews of \"sgr preview\"");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// the root and path of each repo
+    type Repos = Vec<(usize, PathBuf)>;
+
+    /// what `scan_at` passes on and what it returns, once uncached and once
+    /// cached
+    fn scan_twice(cache_txt: &Path, roots: &[Root]) -> Vec<(Repos, Repos)> {
+        (0..2)
+            .map(|_| {
+                let hits = Mutex::new(Vec::new());
+                let found = scan_at(
+                    Ok(cache_txt),
+                    roots,
+                    &Settings::default(),
+                    &Report::default(),
+                    |repo| {
+                        hits.lock().unwrap().push((repo.root, repo.path.clone()));
+                        true
+                    },
+                );
+                let mut hits = hits.into_inner().unwrap();
+                hits.sort();
+                let found = found
+                    .into_iter()
+                    .map(|repo| (repo.root, repo.path))
+                    .collect();
+                (hits, found)
+            })
+            .collect()
+    }
+
+    fn entry(options: &str, repos: &[&str], stamps: &[(&Path, SystemTime)]) -> Entry {
+        Entry {
+            options: options.to_string(),
+            repos: repos
+                .iter()
+                .filter_map(|path| Repo::from_names(0, "git", "clone", path))
+                .collect(),
+            stamps: stamps
+                .iter()
+                .map(|(path, mtime)| Stamp {
+                    root: 0,
+                    path: path.to_path_buf(),
+                    mtime: *mtime,
+                })
+                .collect(),
+        }
+    }
+
+    fn mtime(path: &Path) -> SystemTime {
+        fs::metadata(path).unwrap().modified().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_txt = dir.path().join("cache.txt");
+        let mtime = SystemTime::UNIX_EPOCH + Duration::new(1700000000, 123);
+        let mut cache = HashMap::new();
+        cache.insert(
+            PathBuf::from("/work"),
+            entry(
+                "nested=false",
+                &["/work/a b", "/work/c"],
+                &[(Path::new("/work"), mtime)],
+            ),
+        );
+        cache.insert(PathBuf::from("/home"), entry("nested=true", &[], &[]));
+        save_to(&cache_txt, &cache).unwrap();
+        assert!(!dir.path().join("temp_cache.txt").exists());
+
+        let loaded = load_from(&cache_txt);
+        assert_eq!(loaded.len(), 2);
+        let work = &loaded[Path::new("/work")];
+        assert_eq!(work.options, "nested=false");
+        let repos: Vec<_> = work.repos.iter().map(|repo| &repo.path).collect();
+        assert_eq!(repos, [Path::new("/work/a b"), Path::new("/work/c")]);
+        assert!(work.repos.iter().all(|repo| repo.vcs == "git"));
+        assert_eq!(work.stamps.len(), 1);
+        assert_eq!(work.stamps[0].path, Path::new("/work"));
+        assert_eq!(work.stamps[0].mtime, mtime);
+        assert!(loaded[Path::new("/home")].repos.is_empty());
+    }
+
+    #[test]
+    fn another_version_is_an_empty_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_txt = dir.path().join("cache.txt");
+        fs::write(
+            &cache_txt,
+            "sgr-cache 1\nroot\tnested=false\t/work\nrepo\tgit\tclone\t/work/a\n",
+        )
+        .unwrap();
+        assert!(load_from(&cache_txt).is_empty());
+        fs::write(&cache_txt, "").unwrap();
+        assert!(load_from(&cache_txt).is_empty());
+    }
+
+    #[test]
+    fn missing_cache_is_an_empty_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_from(&dir.path().join("cache.txt")).is_empty());
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_txt = dir.path().join("cache.txt");
+        fs::write(
+            &cache_txt,
+            format!(
+                "{}\nrepo\tgit\tclone\t/orphan\nroot\t\t/work\nrepo\tsvn\tclone\t/work/a\n\
+                 repo\tgit\tclone\t/work/b\nstamp\tyesterday\t/work\nnonsense\n",
+                VERSION
+            ),
+        )
+        .unwrap();
+        let loaded = load_from(&cache_txt);
+        assert_eq!(loaded.len(), 1);
+        let work = &loaded[Path::new("/work")];
+        let repos: Vec<_> = work.repos.iter().map(|repo| &repo.path).collect();
+        assert_eq!(repos, [Path::new("/work/b")]);
+        assert!(work.stamps.is_empty());
+    }
+
+    #[test]
+    fn fresh_until_a_stamp_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ignore = dir.path().join(".sgrignore");
+        fs::write(&ignore, "build\n").unwrap();
+        let stamped = entry(
+            "",
+            &[],
+            &[(dir.path(), mtime(dir.path())), (&ignore, mtime(&ignore))],
+        );
+        assert!(stamped.is_fresh());
+
+        let older = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&ignore)
+            .unwrap()
+            .set_modified(older)
+            .unwrap();
+        assert!(!stamped.is_fresh());
+
+        let gone = entry("", &[], &[(&dir.path().join("gone"), older)]);
+        assert!(!gone.is_fresh());
+    }
+
+    #[test]
+    fn times() {
+        let time = SystemTime::UNIX_EPOCH + Duration::new(1700000000, 5);
+        assert_eq!(format_time(&time), "1700000000.5");
+        assert_eq!(parse_time(&format_time(&time)), Some(time));
+        assert_eq!(parse_time("1700000000"), None);
+        assert_eq!(parse_time("a.b"), None);
+    }
+
+    #[test]
+    fn cached_repos_keep_their_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots: Vec<Root> = ["r1", "r2"]
+            .iter()
+            .map(|name| {
+                let head = dir.path().join(name).join("repo/.git/HEAD");
+                fs::create_dir_all(head.parent().unwrap()).unwrap();
+                fs::write(head, "ref: refs/heads/main\n").unwrap();
+                Root::new(dir.path().join(name))
+            })
+            .collect();
+        let cache_txt = dir.path().join("cache.txt");
+        let expected = vec![
+            (0, dir.path().join("r1/repo")),
+            (1, dir.path().join("r2/repo")),
+        ];
+
+        let runs = scan_twice(&cache_txt, &roots);
+        assert!(cache_txt.exists());
+        for (hits, found) in runs {
+            assert_eq!(hits, expected);
+            assert_eq!(found, expected);
+        }
+    }
+}
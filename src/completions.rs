@@ -0,0 +1,268 @@
+// std lib
+use std::fs::OpenOptions;
+use std::io::BufReader;
+
+// own
+use crate::flags::Flags;
+use crate::init::Shell;
+use crate::utils::{format_log, read_lines, LogLevel, StringExt};
+use crate::{config, to_command, CMD, COMMANDS};
+
+/// shells there are completions for
+const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "pwsh"];
+
+/// Prints the completion script for a shell, or what the arg of another
+/// command can be, one `value<TAB>description` per line. The scripts call
+/// back into sgr for the latter so dirs.txt is read as it is at the time.
+pub fn execute(arg: Option<&str>, flags: &Flags) -> Result<String, String> {
+    let arg = arg.ok_or(format_log(
+        LogLevel::Error,
+        "missing shell for 'completions'; expected bash, zsh, fish or pwsh".to_string(),
+    ))?;
+    if let Ok(command) = to_command(arg) {
+        return values(&command);
+    }
+
+    let shell = match arg {
+        "bash" | "zsh" | "fish" | "pwsh" => Shell::parse(arg)?,
+        _ => {
+            return Err(format_log(
+                LogLevel::Error,
+                format!("unknown shell '{}'; expected bash, zsh, fish or pwsh", arg),
+            ))
+        }
+    };
+    let exe = std::env::current_exe().map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to get sgr.exe path: {}", e),
+        )
+    })?;
+    // the function from `init` as well as sugar itself
+    let names = ["sugar", flags.function.as_deref().unwrap_or("sgr")];
+
+    let template = match shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+        _ => PWSH,
+    };
+    let (names, commands) = match shell {
+        Shell::Pwsh => (
+            names.map(|name| format!("'{}'", name)).join(", "),
+            COMMANDS.map(|command| format!("'{}'", command)).join(", "),
+        ),
+        _ => (names.join(" "), COMMANDS.join(" ")),
+    };
+    let script = template
+        .replace("{names}", &names)
+        .replace("{commands}", &commands)
+        .replace("{exe}", &shell.quote(&exe.display().to_string()));
+    // printed with a newline after it
+    Ok(script.trim_end().to_string())
+}
+
+/// what `command`'s arg can be; paths for `add` and `preview` are left to
+/// the shell
+fn values(command: &CMD) -> Result<String, String> {
+    let fixed: &[&str] = match command {
+        CMD::Remove | CMD::List => return dirs_txt(),
+        CMD::Help => &COMMANDS,
+        CMD::Cache => &["rebuild", "clear"],
+        CMD::Daemon => &["start", "stop", "status", "run"],
+        CMD::History => &["show", "edit", "prune", "clear"],
+        CMD::Init => &["bash", "zsh", "fish", "nushell", "pwsh", "cmd"],
+        CMD::Completions => &SHELLS,
+        _ => &[],
+    };
+    Ok(fixed
+        .iter()
+        .map(|value| format!("{}\t", value))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// each line number of dirs.txt with its path, then each path
+fn dirs_txt() -> Result<String, String> {
+    let dirs_txt_path = config::dirs_txt_path()?;
+    let file = OpenOptions::new()
+        .read(true)
+        .open(&dirs_txt_path)
+        .map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"dirs.txt\": {}", e),
+            )
+        })?;
+    let paths: Vec<String> = read_lines(BufReader::new(file))
+        .map(|line| match line.split_once('|') {
+            Some((path, _)) => path.trim().to_string(),
+            None => line.trim().to_string(),
+        })
+        .collect();
+
+    let lines = paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| format!("{}\t{}", i + 1, path));
+    let paths = paths
+        .iter()
+        .enumerate()
+        .filter(|(_, path)| !path.is_empty())
+        .map(|(i, path)| format!("{}\tline {}", path, i + 1));
+    Ok(lines.chain(paths).collect::<Vec<String>>().join("\n"))
+}
+
+const BASH: &str = r#"# sgr: completions from `sugar completions bash`
+_sgr_complete() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "{commands}" -- "$cur"))
+        return
+    fi
+    [ "$COMP_CWORD" -eq 2 ] || return
+    case "${COMP_WORDS[1]}" in
+        add|preview)
+            compopt -o filenames
+            COMPREPLY=($(compgen -d -- "$cur"))
+            ;;
+        *)
+            # paths in dirs.txt may have spaces, which are quoted as file names
+            compopt -o filenames
+            local IFS=$'\n'
+            local values
+            values="$(command {exe} completions "${COMP_WORDS[1]}" 2>/dev/null | cut -f1)"
+            COMPREPLY=($(compgen -W "$values" -- "$cur"))
+            ;;
+    esac
+}
+complete -F _sgr_complete {names}
+"#;
+
+const ZSH: &str = r#"# sgr: completions from `sugar completions zsh`
+_sgr() {
+    if (( CURRENT == 2 )); then
+        local -a commands=({commands})
+        _describe command commands
+        return
+    fi
+    (( CURRENT == 3 )) || return 1
+    case "$words[2]" in
+        add|preview)
+            _path_files -/
+            ;;
+        *)
+            local -a values
+            local line
+            for line in ${(f)"$(command {exe} completions "$words[2]" 2>/dev/null)"}; do
+                # _describe takes value:description, so colons in paths are escaped
+                values+=("${${line%%$'\t'*}//:/\\:}:${line#*$'\t'}")
+            done
+            _describe -t values value values
+            ;;
+    esac
+}
+compdef _sgr {names}
+"#;
+
+const FISH: &str = r#"# sgr: completions from `sugar completions fish`
+for sgr_name in {names}
+    complete -c $sgr_name -f
+    complete -c $sgr_name -n __fish_use_subcommand -a '{commands}'
+    complete -c $sgr_name -n '__fish_seen_subcommand_from add preview; and test (count (commandline -opc)) -eq 2' -a '(__fish_complete_directories)'
+    complete -c $sgr_name -n 'not __fish_seen_subcommand_from add preview; and test (count (commandline -opc)) -eq 2' -a "(command {exe} completions (commandline -opc)[2] 2>/dev/null)"
+end
+set -e sgr_name
+"#;
+
+const PWSH: &str = r#"# sgr: completions from `sugar completions pwsh`
+Register-ArgumentCompleter -Native -CommandName {names} -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })
+    # an empty word being completed is not an element yet
+    $position = if ($wordToComplete) { $words.Count - 1 } else { $words.Count }
+
+    if ($position -eq 2 -and @('add', 'preview') -contains $words[1])
+    {
+        return [System.Management.Automation.CompletionCompleters]::CompleteFilename($wordToComplete) |
+            Where-Object { $_.ResultType -eq 'ProviderContainer' }
+    }
+    $values = switch ($position)
+    {
+        1 { @({commands}) | ForEach-Object { "$_`t" } }
+        2 { & {exe} completions $words[1] 2>$null }
+    }
+    $values | Where-Object { $_ -and $_.StartsWith($wordToComplete, 'OrdinalIgnoreCase') } | ForEach-Object {
+        $value, $description = $_ -split "`t", 2
+        if (-not $description) { $description = $value }
+        # paths in dirs.txt may have spaces
+        $text = if ($value -match '\s') { "'$($value -replace "'", "''")'" } else { $value }
+        [System.Management.Automation.CompletionResult]::new($text, $value, 'ParameterValue', $description)
+    }
+}
+"#;
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "completions"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "completions".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!(
+        "{}prints tab completions for commands and their args,",
+        title
+    );
+    println!(
+        "{}for bash, zsh, fish or pwsh\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!(
+            "{}",
+            "eval \"$(sugar completions bash)\"   # ~/.bashrc"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "eval \"$(sugar completions zsh)\"    # ~/.zshrc, after compinit"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "sugar completions fish | source    # config.fish"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "Invoke-Expression (& sugar completions pwsh | Out-String)"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "sugar completions bash --function j"
+                .to_string()
+                .fill_left(17)
+        );
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. Completes command names, directories for \"add\" and \"preview\",");
+        println!("     line numbers and paths in dirs.txt for \"remove\" and \"list\", and");
+        println!("     the args of the other commands");
+        println!("  2. They are for \"sugar\" and the function from \"init\", which is");
+        println!("     \"sgr\" unless \"--function\" says otherwise");
+        println!("  3. \"sugar completions <command>\" prints what the arg of that command");
+        println!("     can be, which is what the completions call back for");
+        println!("  4. In pwsh, only \"sugar\" itself is completed; pwsh does not complete");
+        println!("     functions like \"sgr\" this way");
+    }
+}
@@ -0,0 +1,600 @@
+// standard library
+use std::fs::OpenOptions;
+use std::io::{BufReader, ErrorKind};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// own
+use crate::action::{self, Action};
+use crate::detect::{self, Git, Kind, RepoDetector};
+use crate::flags::Flags;
+use crate::key::Key;
+use crate::rows::Column;
+use crate::selector::{Custom, Selector};
+use crate::utils::{format_log, read_lines, Exit, Failure, LogLevel};
+
+/// config.txt or dirs.txt could not be read or has a mistake in it, which
+/// sgr exits with `Exit::Config` for whatever the command was
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl From<ConfigError> for Failure {
+    fn from(e: ConfigError) -> Failure {
+        Failure::new(Exit::Config, e.0)
+    }
+}
+
+/// a directory from dirs.txt to search for repos, along with its options
+///
+/// each line in dirs.txt is a path optionally followed by `|` and space
+/// separated options:
+/// ```text
+/// C:\Users\me\projects
+/// C:\Users\me\work | nested depth=2 follow one-file-system timeout=5 label=work
+/// ```
+#[derive(Clone)]
+pub struct Root {
+    pub path: PathBuf,
+    /// keep searching inside repos for repos nested in them
+    pub nested: bool,
+    /// how many levels below the root to look for repos; `None` falls back
+    /// to the `depth` setting
+    pub depth: Option<usize>,
+    /// walk into symlinked directories
+    pub follow: bool,
+    /// do not walk into directories on another filesystem than the root,
+    /// like mounted network shares
+    pub one_file_system: bool,
+    /// stop searching this root once a search has taken this long
+    pub timeout: Option<Duration>,
+    /// what the picker calls it; the directory's name if `None`
+    pub label: Option<String>,
+    /// line of dirs.txt it is on, from 1
+    pub line: usize,
+}
+
+impl Root {
+    pub fn new(path: PathBuf) -> Root {
+        Root {
+            path,
+            nested: false,
+            depth: None,
+            follow: false,
+            one_file_system: false,
+            timeout: None,
+            label: None,
+            line: 0,
+        }
+    }
+
+    /// what the picker calls it
+    pub fn label(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => self
+                .path
+                .file_name()
+                .unwrap_or(self.path.as_os_str())
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
+    /// command line flags win over what dirs.txt says
+    pub fn apply(&mut self, flags: &Flags) {
+        if flags.depth.is_some() {
+            self.depth = flags.depth;
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Root, String> {
+        let (path, opts) = match line.split_once('|') {
+            Some((path, opts)) => (path.trim(), opts),
+            None => (line.trim(), ""),
+        };
+
+        let mut root = Root::new(PathBuf::from(path));
+        for opt in opts.split_whitespace() {
+            match opt.split_once('=') {
+                None if opt == "nested" => root.nested = true,
+                None if opt == "follow" => root.follow = true,
+                None if opt == "one-file-system" => root.one_file_system = true,
+                Some(("depth", depth)) => {
+                    root.depth = Some(depth.parse().map_err(|_| {
+                        format_log(
+                            LogLevel::Error,
+                            format!(
+                                "depth for \"{}\" in dirs.txt must be a number; got '{}'",
+                                path, depth
+                            ),
+                        )
+                    })?)
+                }
+                Some(("label", label)) => root.label = Some(label.to_string()),
+                Some(("timeout", timeout)) => {
+                    root.timeout = Some(parse_secs(timeout).ok_or(format_log(
+                        LogLevel::Error,
+                        format!(
+                            "timeout for \"{}\" in dirs.txt must be seconds; got '{}'",
+                            path, timeout
+                        ),
+                    ))?)
+                }
+                _ => {
+                    return Err(format_log(
+                        LogLevel::Error,
+                        format!("unknown option '{}' for \"{}\" in dirs.txt", opt, path),
+                    ))
+                }
+            }
+        }
+        Ok(root)
+    }
+}
+
+/// global settings from config.txt; every key is optional
+///
+/// ```text
+/// # comments start with '#'
+/// gitignore = true
+/// depth = 4
+/// kinds = clone,worktree
+/// vcs = git,jj
+/// cache = true
+/// one-file-system = true
+/// verbose = false
+/// timeout = 10
+/// selector = fzf
+/// frecency = true
+/// preview = true
+/// status = true
+/// columns = name,root,branch,vcs,path
+/// selector-command = my-picker --prompt "repo> "
+/// selector-options = --height 40% --bind "ctrl-a:select-all"
+/// action = cd
+/// action-command = tmux new-window -c {path} -n {name}
+/// action-keys = ctrl-o:editor,ctrl-v:code,ctrl-t:shell,ctrl-x:command,ctrl-y:print
+/// ```
+pub struct Settings {
+    /// skip whatever a repo's .gitignore ignores when walking inside it
+    pub gitignore: bool,
+    /// max depth for roots without their own `depth=`; unlimited if `None`
+    pub depth: Option<usize>,
+    /// kinds of repos to list; every kind if `None`
+    pub kinds: Option<Vec<Kind>>,
+    /// detectors to check every directory with, in detection order
+    pub detectors: Vec<&'static dyn RepoDetector>,
+    /// show repos from cache.txt before searching
+    pub cache: bool,
+    /// `one-file-system` for every root
+    pub one_file_system: bool,
+    /// report what the walk skipped and why
+    pub verbose: bool,
+    /// stop searching once a search has taken this long, showing what was
+    /// found so far
+    pub timeout: Option<Duration>,
+    /// fzf if it is installed and the built in picker otherwise if `None`.
+    /// `selector-command` is a `Selector::Custom`
+    pub selector: Option<Selector>,
+    /// passed on to the selector after the ones sgr gives it
+    pub selector_options: Vec<String>,
+    /// list repos jumped to often and recently first
+    pub frecency: bool,
+    /// show `sgr preview` next to the list in fzf and sk
+    pub preview: bool,
+    /// show the picker's rows in `columns` instead of plain paths
+    pub status: bool,
+    /// what the picker's rows show when `status` is on, in order
+    pub columns: Vec<Column>,
+    /// what picking a repo with Enter does
+    pub action: Action,
+    /// what the `command` action runs, with `{path}` and `{name}` in it
+    pub action_command: Option<String>,
+    /// keys that pick a repo with another action in the picker
+    pub action_keys: Vec<(Key, Action)>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            gitignore: false,
+            depth: None,
+            kinds: None,
+            detectors: vec![&Git],
+            cache: true,
+            one_file_system: false,
+            verbose: false,
+            timeout: None,
+            selector: None,
+            selector_options: Vec::new(),
+            frecency: true,
+            preview: true,
+            status: true,
+            columns: Column::DEFAULT.to_vec(),
+            action: Action::Cd,
+            action_command: None,
+            action_keys: Action::parse_keys(action::DEFAULT_KEYS).unwrap_or_default(),
+        }
+    }
+}
+
+impl Settings {
+    /// command line flags win over what config.txt says
+    pub fn apply(&mut self, flags: &Flags) {
+        if flags.kinds.is_some() {
+            self.kinds = flags.kinds.clone();
+        }
+        if let Some(detectors) = &flags.detectors {
+            self.detectors = detectors.clone();
+        }
+        if flags.verbose {
+            self.verbose = true;
+        }
+        if flags.timeout.is_some() {
+            self.timeout = flags.timeout;
+        }
+        if flags.selector.is_some() {
+            self.selector = flags.selector.clone();
+        }
+        if let Some(action) = flags.action {
+            self.action = action;
+        }
+        // after the ones from config.txt so they win for options given twice
+        self.selector_options
+            .extend(flags.selector_options.iter().cloned());
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "gitignore" => self.gitignore = parse_bool(key, value)?,
+            "depth" => {
+                self.depth = Some(value.parse().map_err(|_| {
+                    format_log(
+                        LogLevel::Error,
+                        format!("'{}' in config.txt must be a number; got '{}'", key, value),
+                    )
+                })?)
+            }
+            "kinds" => self.kinds = Some(Kind::parse_list(value)?),
+            "vcs" => self.detectors = detect::parse_list(value)?,
+            "cache" => self.cache = parse_bool(key, value)?,
+            "one-file-system" => self.one_file_system = parse_bool(key, value)?,
+            "verbose" => self.verbose = parse_bool(key, value)?,
+            "frecency" => self.frecency = parse_bool(key, value)?,
+            "preview" => self.preview = parse_bool(key, value)?,
+            "status" => self.status = parse_bool(key, value)?,
+            "columns" => self.columns = Column::parse_list(value)?,
+            "selector" => self.selector = Some(Selector::parse(value)?),
+            "selector-command" => {
+                self.selector = Some(Selector::Custom(Custom {
+                    command: value.to_string(),
+                }))
+            }
+            "selector-options" => self.selector_options = split_options(value)?,
+            "action" => self.action = Action::parse(value)?,
+            "action-command" => self.action_command = Some(value.to_string()),
+            "action-keys" => self.action_keys = Action::parse_keys(value)?,
+            "timeout" => {
+                self.timeout = Some(parse_secs(value).ok_or(format_log(
+                    LogLevel::Error,
+                    format!("'{}' in config.txt must be seconds; got '{}'", key, value),
+                ))?)
+            }
+            _ => {
+                return Err(format_log(
+                    LogLevel::Error,
+                    format!("unknown key '{}' in config.txt", key),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format_log(
+            LogLevel::Error,
+            format!(
+                "'{}' in config.txt must be true or false; got '{}'",
+                key, value
+            ),
+        )),
+    }
+}
+
+/// splits `value` at spaces like a shell would, keeping quoted parts whole
+fn split_options(value: &str) -> Result<Vec<String>, String> {
+    let mut options = Vec::new();
+    let mut option: Option<String> = None;
+    let mut quote = None;
+    for c in value.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                option.get_or_insert_with(String::new);
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, c) if c.is_whitespace() => options.extend(option.take()),
+            (_, c) => option.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(format_log(
+            LogLevel::Error,
+            format!(
+                "unclosed quote in 'selector-options' in config.txt: {}",
+                value
+            ),
+        ));
+    }
+    options.extend(option);
+    Ok(options)
+}
+
+/// seconds like `5` or `0.5`
+pub fn parse_secs(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.parse().ok()?).ok()
+}
+
+/// the directory sgr keeps dirs.txt and its other files in: `SGR_CONFIG_DIR`
+/// if it is set, or else the one sgr itself is in
+pub fn config_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = std::env::var_os("SGR_CONFIG_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    Ok(std::env::current_exe()
+        .map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to get sgr.exe path: {}", e),
+            )
+        })?
+        .parent()
+        .ok_or("Failed to get parent directory of sgr.exe")?
+        .to_path_buf())
+}
+
+/// dirs.txt, created on the first run with only `<home>/projects` in it
+pub fn dirs_txt_path() -> Result<PathBuf, String> {
+    let path = config_dir()?.join("dirs.txt");
+    let home = std::env::var_os("HOME").or(std::env::var_os("USERPROFILE"));
+    if let (false, Some(home)) = (path.exists(), home) {
+        let projects = PathBuf::from(home).join("projects");
+        std::fs::write(&path, format!("{}\n", projects.display())).map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to create file \"{:?}\": {}", path, e),
+            )
+        })?;
+    }
+    Ok(path)
+}
+
+/// gitignore style patterns skipped under every root
+pub fn ignore_txt_path() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join("ignore.txt"))
+}
+
+/// config.txt is optional; a missing file means default settings. Options
+/// from the command line are applied on top
+pub fn read_settings(flags: &Flags) -> Result<Settings, ConfigError> {
+    let config_txt_path = config_dir().map_err(ConfigError)?.join("config.txt");
+    let lines: Vec<String> = match OpenOptions::new().read(true).open(&config_txt_path) {
+        Ok(file) => read_lines(BufReader::new(file)).collect(),
+        Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            return Err(ConfigError(format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"{:?}\": {}", config_txt_path, e),
+            )))
+        }
+    };
+    let mut settings = parse_settings(&lines)?;
+    settings.apply(flags);
+    Ok(settings)
+}
+
+/// the settings the lines of config.txt make
+fn parse_settings(lines: &[String]) -> Result<Settings, ConfigError> {
+    let mut settings = Settings::default();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or(ConfigError(format_log(
+            LogLevel::Error,
+            format!("expected 'key = value' in config.txt; got '{}'", line),
+        )))?;
+        settings
+            .set(key.trim(), value.trim())
+            .map_err(ConfigError)?;
+    }
+    // `command` does nothing without `action-command`, so its key is left to
+    // the selector then
+    if settings.action_command.is_none() {
+        settings
+            .action_keys
+            .retain(|(_, action)| *action != Action::Command);
+    }
+    Ok(settings)
+}
+
+/// every non empty line of dirs.txt, with options from the command line
+/// applied on top
+pub fn read_roots(flags: &Flags) -> Result<Vec<Root>, ConfigError> {
+    let dirs_txt_path = dirs_txt_path().map_err(ConfigError)?;
+    let file = OpenOptions::new()
+        .read(true)
+        .open(&dirs_txt_path)
+        .map_err(|e| {
+            ConfigError(format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"{:?}\": {}", dirs_txt_path, e),
+            ))
+        })?;
+
+    read_lines(BufReader::new(file))
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut root = Root::parse(&line).map_err(ConfigError)?;
+            root.line = i + 1;
+            root.apply(flags);
+            Ok(root)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_without_options() {
+        let root = Root::parse("  /home/me/spaces are okay  ").unwrap();
+        assert_eq!(root.path, PathBuf::from("/home/me/spaces are okay"));
+        assert!(!root.nested && !root.follow && !root.one_file_system);
+        assert_eq!(root.depth, None);
+        assert_eq!(root.timeout, None);
+        assert_eq!(root.label(), "spaces are okay");
+    }
+
+    #[test]
+    fn root_with_options() {
+        let root = Root::parse(
+            "/home/me/work |  nested depth=2 follow one-file-system timeout=1.5 label=job",
+        )
+        .unwrap();
+        assert_eq!(root.path, PathBuf::from("/home/me/work"));
+        assert!(root.nested && root.follow && root.one_file_system);
+        assert_eq!(root.depth, Some(2));
+        assert_eq!(root.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(root.label(), "job");
+    }
+
+    #[test]
+    fn root_options_after_the_first_bar() {
+        let root = Root::parse("/home/me/work|nested").unwrap();
+        assert_eq!(root.path, PathBuf::from("/home/me/work"));
+        assert!(root.nested);
+        // nothing after the bar is no options
+        assert!(!Root::parse("/home/me/work |").unwrap().nested);
+    }
+
+    #[test]
+    fn root_with_bad_options() {
+        for line in [
+            "/home/me | nest",
+            "/home/me | depth=two",
+            "/home/me | depth",
+            "/home/me | timeout=soon",
+            "/home/me | nested=yes",
+        ] {
+            assert!(Root::parse(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn flags_win_over_root_options() {
+        let mut root = Root::parse("/home/me | depth=2").unwrap();
+        root.apply(&Flags::default());
+        assert_eq!(root.depth, Some(2));
+        let flags = Flags {
+            depth: Some(5),
+            ..Flags::default()
+        };
+        root.apply(&flags);
+        assert_eq!(root.depth, Some(5));
+    }
+
+    fn parse(lines: &[&str]) -> Result<Settings, ConfigError> {
+        parse_settings(
+            &lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn key_names(settings: &Settings) -> Vec<String> {
+        settings
+            .action_keys
+            .iter()
+            .map(|(key, action)| format!("{}:{}", key.name(), action.name()))
+            .collect()
+    }
+
+    #[test]
+    fn settings_from_an_empty_config() {
+        let settings = parse(&[]).unwrap();
+        assert!(!settings.gitignore && settings.cache && settings.status);
+        assert_eq!(settings.depth, None);
+        // no `action-command`, so ctrl-x is left alone
+        assert!(!key_names(&settings).contains(&"ctrl-x:command".to_string()));
+        assert!(key_names(&settings).contains(&"ctrl-y:print".to_string()));
+    }
+
+    #[test]
+    fn settings_skip_comments_and_blank_lines() {
+        let settings = parse(&[
+            "# gitignore = true",
+            "",
+            "   gitignore=true  ",
+            "depth = 4",
+            "timeout = 0.5",
+            "kinds = clone, worktree",
+            "vcs = jj,git",
+            "columns = name,path",
+            "action = editor",
+        ])
+        .unwrap();
+        assert!(settings.gitignore);
+        assert_eq!(settings.depth, Some(4));
+        assert_eq!(settings.timeout, Some(Duration::from_millis(500)));
+        assert_eq!(settings.kinds.map(|kinds| kinds.len()), Some(2), "kinds");
+        let vcs: Vec<&str> = settings.detectors.iter().map(|d| d.name()).collect();
+        assert_eq!(vcs, ["jj", "git"]);
+        assert!(settings.columns == [Column::Name, Column::Path]);
+        assert!(settings.action == Action::Editor);
+    }
+
+    #[test]
+    fn settings_keep_the_value_after_the_first_equals() {
+        let settings = parse(&["action-command = echo a=b {path}"]).unwrap();
+        assert_eq!(settings.action_command.as_deref(), Some("echo a=b {path}"));
+        assert!(key_names(&settings).contains(&"ctrl-x:command".to_string()));
+    }
+
+    #[test]
+    fn settings_split_selector_options_like_a_shell() {
+        let settings =
+            parse(&[r#"selector-options = --height 40% --bind "ctrl-a:select all" '' "#]).unwrap();
+        assert_eq!(
+            settings.selector_options,
+            ["--height", "40%", "--bind", "ctrl-a:select all", ""]
+        );
+        assert!(parse(&[r#"selector-options = --prompt "repo> "#]).is_err());
+    }
+
+    #[test]
+    fn settings_with_mistakes() {
+        for line in [
+            "gitignore",
+            "gitignore = yes",
+            "depth = -1",
+            "timeout = never",
+            "kinds = clones",
+            "vcs = svn",
+            "columns = ,",
+            "action = open",
+            "action-keys = ctrl-o",
+            "colour = true",
+        ] {
+            assert!(parse(&[line]).is_err(), "{}", line);
+        }
+    }
+}
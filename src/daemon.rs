@@ -0,0 +1,538 @@
+// standard library
+#[cfg(target_os = "linux")]
+use std::collections::{HashMap, HashSet};
+#[cfg(target_os = "linux")]
+use std::ffi::OsStr;
+#[cfg(target_os = "linux")]
+use std::fs::OpenOptions;
+#[cfg(target_os = "linux")]
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+#[cfg(target_os = "linux")]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(target_os = "linux")]
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::process::{Command, Stdio};
+#[cfg(target_os = "linux")]
+use std::sync::{Arc, Mutex};
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+// third party
+#[cfg(target_os = "linux")]
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+// own
+use crate::config;
+#[cfg(target_os = "linux")]
+use crate::config::{ConfigError, Root, Settings};
+use crate::discover::Repo;
+#[cfg(target_os = "linux")]
+use crate::discover::{self, Report, Stamp};
+use crate::flags::Flags;
+#[cfg(target_os = "linux")]
+use crate::utils::elogln;
+use crate::utils::{format_log, Failure, LogLevel, StringExt};
+
+/// files in the config dir that change what the daemon finds
+#[cfg(target_os = "linux")]
+const CONFIG_FILES: [&str; 3] = ["dirs.txt", "config.txt", "ignore.txt"];
+
+/// what the daemon currently knows, shared between the watcher and the socket
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct Index {
+    repos: Vec<Repo>,
+    roots: usize,
+    watches: usize,
+    /// why the config could not be reloaded the last time it changed
+    config_error: Option<String>,
+}
+
+/// how long a client gets to send its command before it is dropped, and how
+/// long a client waits for the daemon to answer before giving up on it
+#[cfg(target_os = "linux")]
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn socket_path() -> Result<PathBuf, String> {
+    Ok(config::config_dir()?.join("daemon.sock"))
+}
+
+pub fn execute(arg: Option<&str>, _flags: &Flags) -> Result<(), Failure> {
+    let arg = arg.ok_or(format_log(
+        LogLevel::Error,
+        "missing arg for 'daemon'".to_string(),
+    ))?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let socket_path = socket_path()?;
+        match arg {
+            "start" => {
+                read_config()?;
+                Ok(start(&socket_path)?)
+            }
+            "stop" => {
+                request(&socket_path, "stop").map_err(not_answering)?;
+                println!("stopped daemon");
+                Ok(())
+            }
+            "status" => {
+                let status = request(&socket_path, "status").map_err(not_answering)?;
+                print!("{}", status);
+                Ok(())
+            }
+            "run" => {
+                read_config()?;
+                Ok(serve(&socket_path)?)
+            }
+            _ => Err(Failure::from(format_log(
+                LogLevel::Error,
+                format!(
+                    "unknown arg '{}' for 'daemon'; expected start, stop, status or run",
+                    arg
+                ),
+            ))),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    Err(Failure::from(format_log(
+        LogLevel::Error,
+        format!("'daemon {}' is only supported on linux", arg),
+    )))
+}
+
+/// dirs.txt and config.txt as the daemon uses them; `start` and `run` read
+/// them first so mistakes in them are reported there and not in the
+/// background
+#[cfg(target_os = "linux")]
+fn read_config() -> Result<(Vec<Root>, Settings), ConfigError> {
+    let flags = Flags::default();
+    let mut roots = config::read_roots(&flags)?;
+    let mut settings = config::read_settings(&flags)?;
+    // nobody waits on a search in the background
+    roots.iter_mut().for_each(|root| root.timeout = None);
+    settings.timeout = None;
+    Ok((roots, settings))
+}
+
+/// The daemon's repos, or `None` if it is not running so the caller has to
+/// search by itself.
+pub fn query() -> Option<Vec<Repo>> {
+    #[cfg(target_os = "linux")]
+    {
+        let response = request(&socket_path().ok()?, "list").ok()?;
+        let repos = response
+            .lines()
+            .filter_map(
+                |line| match line.splitn(4, '\t').collect::<Vec<&str>>()[..] {
+                    [root, vcs, kind, path] => {
+                        Repo::from_names(root.parse().ok()?, vcs, kind, path)
+                    }
+                    _ => None,
+                },
+            )
+            .collect();
+        Some(repos)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn not_running() -> String {
+    format_log(LogLevel::Warn, "daemon is not running".to_string())
+}
+
+/// why `request` got no answer
+#[cfg(target_os = "linux")]
+fn not_answering(e: std::io::Error) -> String {
+    match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => format_log(
+            LogLevel::Warn,
+            "daemon is not answering; it may still be on its first search".to_string(),
+        ),
+        _ => not_running(),
+    }
+}
+
+/// Sends a one line command and reads the whole response. Gives up after
+/// `CLIENT_TIMEOUT`, like while the daemon is still on its first search, so
+/// callers can search by themselves instead.
+#[cfg(target_os = "linux")]
+fn request(socket_path: &PathBuf, command: &str) -> std::io::Result<String> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    writeln!(stream, "{}", command)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+/// starts `sgr daemon run` in the background, detached from the terminal,
+/// with what it has to say going to daemon.log next to dirs.txt
+#[cfg(target_os = "linux")]
+fn start(socket_path: &PathBuf) -> Result<(), String> {
+    if request(socket_path, "status").is_ok() {
+        return Err(format_log(
+            LogLevel::Warn,
+            "daemon is already running".to_string(),
+        ));
+    }
+
+    let exe = std::env::current_exe().map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to get sgr.exe path: {}", e),
+        )
+    })?;
+    let log_path = config::config_dir()?.join("daemon.log");
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"{}\": {}", log_path.display(), e),
+            )
+        })?;
+    let child = Command::new(exe)
+        .args(["daemon", "run"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log)
+        // own process group so closing the terminal does not take it down
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format_log(LogLevel::Error, format!("failed to start daemon: {}", e)))?;
+
+    println!("started daemon (pid {})", child.id());
+    Ok(())
+}
+
+/// Runs the daemon in the foreground: keeps the index current in one thread
+/// and answers `list`, `status` and `stop` over the socket, one thread per
+/// client so a client that never sends anything holds up nobody else.
+///
+/// The socket is bound right away but only accepted from after the first
+/// scan, so early clients wait for a complete index instead of an empty one.
+#[cfg(target_os = "linux")]
+fn serve(socket_path: &PathBuf) -> Result<(), String> {
+    // a socket left behind by a daemon that did not stop cleanly
+    if socket_path.exists() && UnixStream::connect(socket_path).is_err() {
+        _ = std::fs::remove_file(socket_path);
+    }
+    let listener = UnixListener::bind(socket_path).map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to bind \"{}\": {}", socket_path.display(), e),
+        )
+    })?;
+
+    let index = Arc::new(Mutex::new(Index::default()));
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let watcher_index = Arc::clone(&index);
+    let watcher_socket_path = socket_path.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = watch(&watcher_index, || _ = ready_tx.send(())) {
+            elogln(LogLevel::Error, e);
+            // nobody is left to answer on it
+            _ = std::fs::remove_file(&watcher_socket_path);
+            std::process::exit(1);
+        }
+    });
+    _ = ready_rx.recv();
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let (index, socket_path) = (Arc::clone(&index), socket_path.clone());
+        std::thread::spawn(move || answer(stream, &index, &socket_path));
+    }
+    Ok(())
+}
+
+/// reads one command from a client and writes back the response
+#[cfg(target_os = "linux")]
+fn answer(mut stream: UnixStream, index: &Mutex<Index>, socket_path: &PathBuf) {
+    _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+    _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+    let mut command = String::new();
+    if BufReader::new(&stream).read_line(&mut command).is_err() {
+        return;
+    }
+
+    let response = {
+        let Ok(index) = index.lock() else {
+            return;
+        };
+        match command.trim() {
+            "list" => index
+                .repos
+                .iter()
+                .map(|repo| {
+                    format!(
+                        "{}\t{}\t{}\t{}\n",
+                        repo.root,
+                        repo.vcs,
+                        repo.kind.name(),
+                        repo.path.display()
+                    )
+                })
+                .collect(),
+            "status" => {
+                let mut status = format!(
+                    "pid {}\nroots {}\nrepos {}\nwatches {}\n",
+                    std::process::id(),
+                    index.roots,
+                    index.repos.len(),
+                    index.watches
+                );
+                if let Some(e) = &index.config_error {
+                    status += &format!("kept the previous config after:\n{}\n", e);
+                }
+                status
+            }
+            "stop" => {
+                _ = writeln!(stream, "ok");
+                _ = std::fs::remove_file(socket_path);
+                std::process::exit(0);
+            }
+            command => format!("unknown command '{}'\n", command),
+        }
+    };
+    _ = stream.write_all(response.as_bytes());
+}
+
+/// Watches every directory a scan read, plus the config files, with inotify.
+///
+/// Anything added, removed or renamed in a watched directory means its root
+/// is scanned again; a change to dirs.txt, config.txt or ignore.txt reloads
+/// them and scans every root again. If they cannot be read then, the error
+/// is logged and the previous ones are kept. Events that did not fit in the
+/// kernel's queue are lost, so every root is scanned again after that. Events
+/// are gathered for a short while first since a clone or a move comes as a
+/// burst of them.
+#[cfg(target_os = "linux")]
+fn watch<F>(index: &Mutex<Index>, ready: F) -> Result<(), String>
+where
+    F: Fn(),
+{
+    let mut inotify = Inotify::init()
+        .map_err(|e| format_log(LogLevel::Error, format!("failed to start inotify: {}", e)))?;
+    let config_dir = config::config_dir()?;
+    let config_wd = inotify
+        .watches()
+        .add(
+            &config_dir,
+            WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE,
+        )
+        .map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to watch \"{}\": {}", config_dir.display(), e),
+            )
+        })?;
+    let dir_mask = WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::MOVED_FROM
+        | WatchMask::MOVED_TO
+        | WatchMask::DELETE_SELF
+        | WatchMask::MOVE_SELF;
+    // the ignore files the scan read
+    let file_mask = WatchMask::CLOSE_WRITE | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF;
+
+    let mut roots: Vec<Root> = Vec::new();
+    let mut settings = Settings::default();
+    let mut watched: HashMap<WatchDescriptor, (usize, PathBuf)> = HashMap::new();
+    // every root is dirty at first, and again after the config changes
+    let mut reload = true;
+    let mut dirty: HashSet<usize> = HashSet::new();
+    let mut buffer = [0; 4096];
+
+    loop {
+        if reload {
+            match read_config() {
+                Ok((new_roots, new_settings)) => {
+                    (roots, settings) = (new_roots, new_settings);
+                    dirty = (0..roots.len()).collect();
+                    for (wd, _) in watched.drain() {
+                        _ = inotify.watches().remove(wd);
+                    }
+                    if let Ok(mut index) = index.lock() {
+                        index.repos.clear();
+                        index.config_error = None;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e.0);
+                    elogln(
+                        LogLevel::Warn,
+                        "kept the previous dirs.txt and config.txt".to_string(),
+                    );
+                    if let Ok(mut index) = index.lock() {
+                        index.config_error = Some(e.0);
+                    }
+                }
+            }
+        }
+
+        let rescan: Vec<usize> = dirty.drain().collect();
+        let (repos, stamps) = rescan_roots(&roots, &settings, &rescan);
+
+        // swap the watches of the rescanned roots for the directories read now
+        watched.retain(|wd, (root, _)| {
+            let keep = !rescan.contains(root);
+            if !keep {
+                _ = inotify.watches().remove(wd.clone());
+            }
+            keep
+        });
+        for stamp in stamps {
+            let mask = match stamp.path.is_file() {
+                true => file_mask,
+                false => dir_mask,
+            };
+            match inotify.watches().add(&stamp.path, mask) {
+                Ok(wd) => {
+                    watched.insert(wd, (stamp.root, stamp.path));
+                }
+                // most likely out of watches; see fs.inotify.max_user_watches
+                Err(e) if e.raw_os_error() == Some(28) => {
+                    elogln(
+                        LogLevel::Warn,
+                        format!("Failed to watch \"{}\": {}", stamp.path.display(), e),
+                    );
+                }
+                Err(_) => {}
+            }
+        }
+
+        if let Ok(mut index) = index.lock() {
+            index.repos.retain(|repo| !rescan.contains(&repo.root));
+            index.repos.extend(repos);
+            index
+                .repos
+                .sort_by(|a, b| a.root.cmp(&b.root).then_with(|| a.path.cmp(&b.path)));
+            index.roots = roots.len();
+            index.watches = watched.len() + 1;
+        }
+        if reload {
+            ready();
+            reload = false;
+        }
+
+        // wait for the first event, then gather the rest of the burst
+        let mut events: Vec<(WatchDescriptor, EventMask, Option<String>)> = Vec::new();
+        let mut read = |events: &mut Vec<_>, blocking: bool| -> Result<(), String> {
+            let read = match blocking {
+                true => inotify.read_events_blocking(&mut buffer),
+                false => inotify.read_events(&mut buffer),
+            };
+            match read {
+                Ok(read) => {
+                    events.extend(read.map(|event| {
+                        let name = event.name.map(OsStr::to_string_lossy);
+                        (event.wd, event.mask, name.map(|name| name.to_string()))
+                    }));
+                    Ok(())
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(()),
+                Err(e) => Err(format_log(
+                    LogLevel::Error,
+                    format!("failed to read inotify events: {}", e),
+                )),
+            }
+        };
+        read(&mut events, true)?;
+        std::thread::sleep(Duration::from_millis(300));
+        read(&mut events, false)?;
+
+        for (wd, mask, name) in events {
+            if mask.contains(EventMask::Q_OVERFLOW) {
+                dirty.extend(0..roots.len());
+            } else if wd == config_wd {
+                reload |= name.is_some_and(|name| CONFIG_FILES.contains(&name.as_str()));
+            } else if let Some((root, _)) = watched.get(&wd) {
+                dirty.insert(*root);
+            }
+        }
+    }
+}
+
+/// scans only the roots at `indices`, with results indexed like `roots`
+#[cfg(target_os = "linux")]
+fn rescan_roots(roots: &[Root], settings: &Settings, indices: &[usize]) -> (Vec<Repo>, Vec<Stamp>) {
+    let subset: Vec<Root> = indices.iter().map(|&i| roots[i].clone()).collect();
+    let report = Report::default();
+    let (repos, stamps) =
+        discover::scan_stamped(&subset, settings, &report, |_| true).unwrap_or_default();
+    // stderr is daemon.log
+    report.print(&subset, settings);
+
+    let repos = repos
+        .into_iter()
+        .map(|repo| Repo {
+            root: indices[repo.root],
+            ..repo
+        })
+        .collect();
+    let stamps = stamps
+        .into_iter()
+        .map(|stamp| Stamp {
+            root: indices[stamp.root],
+            ..stamp
+        })
+        .collect();
+    (repos, stamps)
+}
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "daemon"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "daemon".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!("{}starts, stops or checks a background process that", title);
+    println!(
+        "{}keeps the list of repos current as they change (linux only)\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!(
+            "{}While it runs, sgr asks it for repos instead of searching",
+            "".to_string().pad_right(15).fill_left(2)
+        );
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!("{}", "sgr daemon start".to_string().fill_left(17));
+        println!("{}", "sgr daemon stop".to_string().fill_left(17));
+        println!("{}", "sgr daemon status".to_string().fill_left(17));
+        println!("{}", "sgr daemon run".to_string().fill_left(17));
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. \"run\" runs it in the foreground, \"start\" runs it in the background");
+        println!(
+            "  2. It listens on daemon.sock and \"start\" logs to daemon.log, next to dirs.txt"
+        );
+        println!(
+            "  3. It watches every directory it searched, so very large directories in dirs.txt"
+        );
+        println!("     may need a higher fs.inotify.max_user_watches");
+        println!("  4. Flags that change how repos are searched, like --depth, skip the daemon");
+        println!("  5. A dirs.txt or config.txt that cannot be read is logged, and the previous");
+        println!("     ones are kept");
+    }
+}
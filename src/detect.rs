@@ -0,0 +1,360 @@
+// standard library
+use std::ffi::OsStr;
+use std::path::Path;
+
+// own
+use crate::utils::{format_log, LogLevel};
+
+/// how a directory is a repo
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// a regular checkout, like a git clone with a `.git` directory
+    Clone,
+    /// a linked checkout sharing another one's store, like `git worktree add`
+    /// or `jj workspace add`
+    Worktree,
+    /// a git submodule checkout; `.git` is a file pointing into the super repo
+    Submodule,
+    /// a repo without a working tree
+    Bare,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Clone, Kind::Worktree, Kind::Submodule, Kind::Bare];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Clone => "clone",
+            Kind::Worktree => "worktree",
+            Kind::Submodule => "submodule",
+            Kind::Bare => "bare",
+        }
+    }
+
+    /// comma separated kind names, like `clone,worktree`
+    pub fn parse_list(list: &str) -> Result<Vec<Kind>, String> {
+        list.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                Kind::ALL
+                    .into_iter()
+                    .find(|kind| kind.name() == name)
+                    .ok_or(format_log(
+                        LogLevel::Error,
+                        format!(
+                            "unknown kind '{}'; expected clone, worktree, submodule or bare",
+                            name
+                        ),
+                    ))
+            })
+            .collect()
+    }
+}
+
+/// recognizes checkouts of one version control system
+pub trait RepoDetector: Sync {
+    /// shown next to each repo and used in config.txt's `vcs`
+    fn name(&self) -> &'static str;
+
+    /// how `dir` is a repo of this vcs, if it is one at all
+    fn detect(&self, dir: &Path) -> Option<Kind>;
+
+    /// whether a directory named `name` only holds this vcs' internals, so
+    /// there is never a checkout under it
+    fn is_internal(&self, name: &OsStr) -> bool;
+}
+
+/// every built in detector, in the order they are tried. Jujutsu comes before
+/// git so a colocated `.jj` + `.git` checkout is reported once, as jj.
+pub static DETECTORS: [&dyn RepoDetector; 5] = [&Jujutsu, &Git, &Mercurial, &Fossil, &Pijul];
+
+/// comma separated vcs names, like `git,jj`, in detection order
+pub fn parse_list(list: &str) -> Result<Vec<&'static dyn RepoDetector>, String> {
+    let names: Vec<&str> = list
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if let Some(name) = names
+        .iter()
+        .find(|name| DETECTORS.iter().all(|d| d.name() != **name))
+    {
+        return Err(format_log(
+            LogLevel::Error,
+            format!(
+                "unknown vcs '{}'; expected one of git, jj, hg, fossil, pijul",
+                name
+            ),
+        ));
+    }
+
+    Ok(DETECTORS
+        .into_iter()
+        .filter(|d| names.contains(&d.name()))
+        .collect())
+}
+
+pub struct Git;
+
+impl RepoDetector for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn detect(&self, dir: &Path) -> Option<Kind> {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return match dot_git.join("HEAD").exists() {
+                true => Some(Kind::Clone),
+                false => None,
+            };
+        }
+
+        // worktrees and submodules have a `gitdir: <path>` file instead
+        if dot_git.is_file() {
+            let contents = std::fs::read_to_string(&dot_git).ok()?;
+            let git_dir = contents.strip_prefix("gitdir:")?.trim();
+            let git_dir = dir.join(git_dir);
+            // only worktree git dirs point back to the main repo's git dir
+            return match git_dir.join("commondir").exists() {
+                true => Some(Kind::Worktree),
+                false => Some(Kind::Submodule),
+            };
+        }
+
+        if dir.join("HEAD").is_file() && dir.join("objects").is_dir() && dir.join("refs").is_dir() {
+            return Some(Kind::Bare);
+        }
+        None
+    }
+
+    fn is_internal(&self, name: &OsStr) -> bool {
+        name == ".git"
+    }
+}
+
+pub struct Jujutsu;
+
+impl RepoDetector for Jujutsu {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn detect(&self, dir: &Path) -> Option<Kind> {
+        // secondary workspaces have a file pointing at the main repo instead
+        let repo = dir.join(".jj").join("repo");
+        match repo.is_dir() {
+            true => Some(Kind::Clone),
+            false if repo.is_file() => Some(Kind::Worktree),
+            false => None,
+        }
+    }
+
+    fn is_internal(&self, name: &OsStr) -> bool {
+        name == ".jj"
+    }
+}
+
+pub struct Mercurial;
+
+impl RepoDetector for Mercurial {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn detect(&self, dir: &Path) -> Option<Kind> {
+        let dot_hg = dir.join(".hg");
+        if !dot_hg.is_dir() {
+            return None;
+        }
+        // made by `hg share`
+        match dot_hg.join("sharedpath").is_file() {
+            true => Some(Kind::Worktree),
+            false => Some(Kind::Clone),
+        }
+    }
+
+    fn is_internal(&self, name: &OsStr) -> bool {
+        name == ".hg"
+    }
+}
+
+pub struct Fossil;
+
+impl RepoDetector for Fossil {
+    fn name(&self) -> &'static str {
+        "fossil"
+    }
+
+    fn detect(&self, dir: &Path) -> Option<Kind> {
+        // the checkout db is `_FOSSIL_` on windows and `.fslckout` elsewhere
+        match dir.join(".fslckout").is_file() || dir.join("_FOSSIL_").is_file() {
+            true => Some(Kind::Clone),
+            false => None,
+        }
+    }
+
+    fn is_internal(&self, _name: &OsStr) -> bool {
+        // fossil keeps its repo in a single file
+        false
+    }
+}
+
+pub struct Pijul;
+
+impl RepoDetector for Pijul {
+    fn name(&self) -> &'static str {
+        "pijul"
+    }
+
+    fn detect(&self, dir: &Path) -> Option<Kind> {
+        match dir.join(".pijul").is_dir() {
+            true => Some(Kind::Clone),
+            false => None,
+        }
+    }
+
+    fn is_internal(&self, name: &OsStr) -> bool {
+        name == ".pijul"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// `files` under `dir`, directories if they end in `/`
+    fn fixture(dir: &Path, files: &[(&str, &str)]) {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            match path.to_string_lossy().ends_with('/') {
+                true => fs::create_dir_all(&path).unwrap(),
+                false => {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(&path, contents).unwrap();
+                }
+            }
+        }
+    }
+
+    /// the vcs and kind the first detector to match says
+    fn detect(dir: &Path) -> Option<(&'static str, &'static str)> {
+        DETECTORS
+            .iter()
+            .find_map(|d| d.detect(dir).map(|kind| (d.name(), kind.name())))
+    }
+
+    #[test]
+    fn git_clone_worktree_and_submodule() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fixture(
+            dir,
+            &[
+                ("main/.git/HEAD", "ref: refs/heads/main\n"),
+                ("main/.git/worktrees/wt/commondir", "../..\n"),
+                ("wt/.git", "gitdir: ../main/.git/worktrees/wt\n"),
+                ("main/.git/modules/sub/HEAD", "ref: refs/heads/main\n"),
+                ("main/sub/.git", "gitdir: ../.git/modules/sub\n"),
+            ],
+        );
+        assert_eq!(detect(&dir.join("main")), Some(("git", "clone")));
+        assert_eq!(detect(&dir.join("wt")), Some(("git", "worktree")));
+        assert_eq!(detect(&dir.join("main/sub")), Some(("git", "submodule")));
+    }
+
+    #[test]
+    fn git_bare() {
+        let dir = tempfile::tempdir().unwrap();
+        let bare = dir.path().join("project.git");
+        fixture(
+            &bare,
+            &[
+                ("HEAD", "ref: refs/heads/main\n"),
+                ("objects/", ""),
+                ("refs/", ""),
+            ],
+        );
+        assert_eq!(detect(&bare), Some(("git", "bare")));
+        // without refs it is just a directory with a HEAD file in it
+        fs::remove_dir(bare.join("refs")).unwrap();
+        assert_eq!(detect(&bare), None);
+    }
+
+    #[test]
+    fn not_a_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        fixture(dir.path(), &[("empty/.git/", ""), ("plain/README", "")]);
+        assert_eq!(detect(&dir.path().join("empty")), None);
+        assert_eq!(detect(&dir.path().join("plain")), None);
+    }
+
+    #[test]
+    fn colocated_jj_and_git_is_jj() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fixture(
+            dir,
+            &[
+                ("co/.jj/repo/", ""),
+                ("co/.git/HEAD", "ref: refs/heads/main\n"),
+                ("ws/.jj/repo", "../../co/.jj/repo"),
+            ],
+        );
+        assert_eq!(detect(&dir.join("co")), Some(("jj", "clone")));
+        assert_eq!(Git.detect(&dir.join("co")).map(|k| k.name()), Some("clone"));
+        assert_eq!(detect(&dir.join("ws")), Some(("jj", "worktree")));
+    }
+
+    #[test]
+    fn hg_clone_and_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fixture(
+            dir,
+            &[
+                ("clone/.hg/requires", ""),
+                ("share/.hg/sharedpath", "/somewhere/clone/.hg"),
+            ],
+        );
+        assert_eq!(detect(&dir.join("clone")), Some(("hg", "clone")));
+        assert_eq!(detect(&dir.join("share")), Some(("hg", "worktree")));
+    }
+
+    #[test]
+    fn fossil_and_pijul() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fixture(
+            dir,
+            &[
+                ("fossil/.fslckout", ""),
+                ("windows/_FOSSIL_", ""),
+                ("pijul/.pijul/", ""),
+            ],
+        );
+        assert_eq!(detect(&dir.join("fossil")), Some(("fossil", "clone")));
+        assert_eq!(detect(&dir.join("windows")), Some(("fossil", "clone")));
+        assert_eq!(detect(&dir.join("pijul")), Some(("pijul", "clone")));
+    }
+
+    #[test]
+    fn vcs_list_keeps_detection_order() {
+        let names = |list: &str| -> Vec<&str> {
+            parse_list(list).unwrap().iter().map(|d| d.name()).collect()
+        };
+        assert_eq!(names("git, jj"), ["jj", "git"]);
+        assert_eq!(names("hg"), ["hg"]);
+        assert!(parse_list("git,svn").is_err());
+    }
+
+    #[test]
+    fn kind_list() {
+        let kinds = Kind::parse_list("bare, clone,").unwrap();
+        assert!(kinds == [Kind::Bare, Kind::Clone]);
+        assert!(Kind::parse_list("clones").is_err());
+    }
+}
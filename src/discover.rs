@@ -0,0 +1,801 @@
+// standard library
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+// third party
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{WalkBuilder, WalkState};
+
+// own
+use crate::config::{self, Root, Settings};
+use crate::detect::{Kind, DETECTORS};
+use crate::utils::{elogln, LogLevel};
+
+/// a repo found under one of the roots in dirs.txt
+#[derive(Clone)]
+pub struct Repo {
+    /// index of the root this repo was found under
+    pub root: usize,
+    pub path: PathBuf,
+    /// name of the detector that found it, like `git`
+    pub vcs: &'static str,
+    pub kind: Kind,
+}
+
+impl Repo {
+    /// rebuilds a repo saved by its vcs and kind names, like in cache.txt
+    pub fn from_names(root: usize, vcs: &str, kind: &str, path: &str) -> Option<Repo> {
+        Some(Repo {
+            root,
+            path: PathBuf::from(path),
+            vcs: DETECTORS.iter().find(|d| d.name() == vcs)?.name(),
+            kind: Kind::ALL.into_iter().find(|k| k.name() == kind)?,
+        })
+    }
+}
+
+/// a directory whose contents were read during a scan, or an ignore file the
+/// scan read in one, with its modified time at that point. Adding, removing
+/// or renaming anything directly in a directory changes that time, and so
+/// does editing a file.
+#[derive(Clone)]
+pub struct Stamp {
+    pub root: usize,
+    pub path: PathBuf,
+    pub mtime: SystemTime,
+}
+
+/// What kept a scan from seeing everything, by root. Loops are not counted
+/// since `follow` expects them.
+#[derive(Default)]
+pub struct Report {
+    roots: Mutex<Vec<(PathBuf, Problems)>>,
+    /// the whole scan ran out of time
+    timed_out: AtomicBool,
+    /// what the walk had to say with `verbose` on, kept until `print` so it
+    /// does not draw over the picker
+    notes: Mutex<Vec<String>>,
+}
+
+#[derive(Default)]
+struct Problems {
+    timed_out: bool,
+    permission_denied: usize,
+    io_errors: usize,
+}
+
+impl Report {
+    /// marks the whole scan as out of time, also for callers that gave up on
+    /// waiting for it
+    pub fn time_out(&self) {
+        self.timed_out.store(true, Ordering::Relaxed);
+    }
+
+    /// whether the scan or any root in it ran out of time
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+            || self
+                .roots
+                .lock()
+                .is_ok_and(|roots| roots.iter().any(|(_, problems)| problems.timed_out))
+    }
+
+    fn update<F>(&self, root: &Path, update: F)
+    where
+        F: FnOnce(&mut Problems),
+    {
+        let mut roots = match self.roots.lock() {
+            Ok(roots) => roots,
+            Err(_) => return,
+        };
+        match roots.iter_mut().find(|(path, _)| path == root) {
+            Some((_, problems)) => update(problems),
+            None => {
+                let mut problems = Problems::default();
+                update(&mut problems);
+                roots.push((root.to_path_buf(), problems));
+            }
+        }
+    }
+
+    fn note(&self, note: String) {
+        if let Ok(mut notes) = self.notes.lock() {
+            notes.push(note);
+        }
+    }
+
+    fn error(&self, root: &Path, e: &ignore::Error) {
+        match e.io_error().map(|e| e.kind()) {
+            Some(ErrorKind::PermissionDenied) => {
+                self.update(root, |problems| problems.permission_denied += 1)
+            }
+            Some(_) => self.update(root, |problems| problems.io_errors += 1),
+            None => {}
+        }
+    }
+
+    /// prints the notes taken with `verbose` on to stderr, then a line for
+    /// the scan if it ran out of time, and one for every root that ran out of
+    /// time or had errors
+    pub fn print(&self, roots: &[Root], settings: &Settings) {
+        if let Ok(notes) = self.notes.lock() {
+            for note in notes.iter() {
+                elogln(LogLevel::Info, note.clone());
+            }
+        }
+        if let (true, Some(timeout)) = (self.timed_out.load(Ordering::Relaxed), settings.timeout) {
+            elogln(
+                LogLevel::Warn,
+                format!("search timed out after {:?}", timeout),
+            );
+        }
+
+        let problems = match self.roots.lock() {
+            Ok(problems) => problems,
+            Err(_) => return,
+        };
+        for (path, problems) in problems.iter() {
+            let mut summary = Vec::new();
+            let timeout = roots
+                .iter()
+                .find(|root| &root.path == path)
+                .and_then(|root| root.timeout);
+            if let (true, Some(timeout)) = (problems.timed_out, timeout) {
+                summary.push(format!("timed out after {:?}", timeout));
+            }
+            if problems.permission_denied > 0 {
+                summary.push(format!("{} permission denied", problems.permission_denied));
+            }
+            if problems.io_errors > 0 {
+                summary.push(format!("{} I/O error(s)", problems.io_errors));
+            }
+            if !summary.is_empty() {
+                elogln(
+                    LogLevel::Warn,
+                    format!("\"{}\": {}", path.display(), summary.join(", ")),
+                );
+            }
+        }
+    }
+}
+
+/// Walks every root at the same time on a work-stealing thread pool.
+///
+/// `on_hit` is called from the walker threads as soon as a repo is found so
+/// callers can stream results; it returns `false` to stop the scan early. The
+/// returned list holds the same repos sorted by root then path, so it does
+/// not depend on thread scheduling.
+pub fn scan<F>(roots: &[Root], settings: &Settings, report: &Report, on_hit: F) -> Vec<Repo>
+where
+    F: Fn(&Repo) -> bool + Sync,
+{
+    collect(roots, settings, report, on_hit, false).0
+}
+
+/// `scan`, but also stamps every directory it read. Returns `None` if
+/// `on_hit` stopped the scan or it ran out of time before it was complete.
+pub fn scan_stamped<F>(
+    roots: &[Root],
+    settings: &Settings,
+    report: &Report,
+    on_hit: F,
+) -> Option<(Vec<Repo>, Vec<Stamp>)>
+where
+    F: Fn(&Repo) -> bool + Sync,
+{
+    match collect(roots, settings, report, on_hit, true) {
+        (repos, stamps, true) => Some((repos, stamps)),
+        (_, _, false) => None,
+    }
+}
+
+fn collect<F>(
+    roots: &[Root],
+    settings: &Settings,
+    report: &Report,
+    on_hit: F,
+    stamp: bool,
+) -> (Vec<Repo>, Vec<Stamp>, bool)
+where
+    F: Fn(&Repo) -> bool + Sync,
+{
+    let found = Mutex::new(Vec::new());
+    let stamps = Mutex::new(Vec::new());
+    let complete = AtomicBool::new(true);
+    walk(
+        roots,
+        settings,
+        report,
+        |repo| {
+            let keep_going = on_hit(&repo);
+            if let Ok(mut found) = found.lock() {
+                found.push(repo);
+            }
+            match keep_going {
+                true => WalkState::Continue,
+                false => {
+                    complete.store(false, Ordering::Relaxed);
+                    WalkState::Quit
+                }
+            }
+        },
+        |dir| {
+            if let (true, Ok(mut stamps)) = (stamp, stamps.lock()) {
+                stamps.push(dir);
+            }
+        },
+    );
+
+    let mut found = found.into_inner().unwrap_or_default();
+    found.sort_by(|a, b| a.root.cmp(&b.root).then_with(|| a.path.cmp(&b.path)));
+    let stamps = stamps.into_inner().unwrap_or_default();
+    (found, stamps, complete.into_inner() && !report.timed_out())
+}
+
+/// the ignore files the walk reads in every directory, and in those above a
+/// root when the `gitignore` setting is on
+pub fn ignore_files(settings: &Settings) -> &'static [&'static str] {
+    match settings.gitignore {
+        true => &[".sgrignore", ".gitignore"],
+        false => &[".sgrignore"],
+    }
+}
+
+/// whether there is at least one repo under `root`; stops at the first hit
+pub fn contains_repo(root: &Root, settings: &Settings) -> bool {
+    let hit = AtomicBool::new(false);
+    walk(
+        std::slice::from_ref(root),
+        settings,
+        &Report::default(),
+        |_| {
+            hit.store(true, Ordering::Relaxed);
+            WalkState::Quit
+        },
+        |_| {},
+    );
+    hit.into_inner()
+}
+
+/// `path` as a repo under one of `roots`, if it is one of a vcs and kind the
+/// settings ask for. Only the path itself is checked, not whether a walk
+/// would get to it.
+pub fn repo_at(roots: &[Root], settings: &Settings, path: &Path) -> Option<Repo> {
+    if !roots.iter().any(|root| path.starts_with(&root.path)) {
+        return None;
+    }
+    let (vcs, kind) = settings
+        .detectors
+        .iter()
+        .find_map(|d| d.detect(path).map(|kind| (d.name(), kind)))?;
+    let wanted = settings
+        .kinds
+        .as_ref()
+        .is_none_or(|kinds| kinds.contains(&kind));
+    wanted.then(|| Repo {
+        root: root_of(roots, path),
+        path: path.to_path_buf(),
+        vcs,
+        kind,
+    })
+}
+
+/// Shared walk behind `scan` and `contains_repo`. Each directory is checked by
+/// the detectors in the `vcs` setting and the first match wins. Once a
+/// directory is a repo its contents are not walked unless its root is marked
+/// `nested`. Bare repos and vcs internals like `.git` are never walked into.
+/// Repos of a kind not in the `kinds` setting are not reported but still stop
+/// the walk the same way.
+///
+/// Nothing deeper than a root's max depth is looked at. Directories matched
+/// by ignore.txt, by a `.sgrignore` file or, when the `gitignore` setting is
+/// on, by the .gitignore of the repo they are in are skipped.
+///
+/// Under roots marked `one-file-system`, or every root when that setting is
+/// on, directories on another filesystem than the root are skipped, and
+/// noted in `report` when the `verbose` setting is on.
+///
+/// Once a search has taken longer than a root's `timeout=`, the rest of that
+/// root is skipped, and once it has taken longer than the `timeout` setting
+/// the whole walk stops. Those and errors reading directories end up in
+/// `report`, along with each error when `verbose` is on.
+///
+/// Symlinked directories are only walked under roots marked `follow`, with
+/// links back to a parent reported as loops and not walked. A repo reachable
+/// through more than one path is only reported the first time it is found.
+///
+/// `on_dir` gets a stamp of every directory the walk reads the contents of,
+/// and of the ignore files in it.
+fn walk<F, D>(roots: &[Root], settings: &Settings, report: &Report, on_hit: F, on_dir: D)
+where
+    F: Fn(Repo) -> WalkState + Sync,
+    D: Fn(Stamp) + Sync,
+{
+    let (first, rest) = match roots.split_first() {
+        Some(roots) => roots,
+        None => return,
+    };
+
+    let mut builder = WalkBuilder::new(walk_path(first));
+    rest.iter().for_each(|root| {
+        builder.add(walk_path(root));
+    });
+    // only the ignore files sgr knows about, no hidden file filtering
+    builder
+        .standard_filters(false)
+        .follow_links(roots.iter().any(|root| root.follow))
+        .add_custom_ignore_filename(".sgrignore")
+        .parents(settings.gitignore)
+        .git_ignore(settings.gitignore)
+        .git_exclude(settings.gitignore);
+    let global_ignores = global_ignores(roots, &config::ignore_txt_path().unwrap_or_default());
+    let devices: Vec<Option<u64>> = roots
+        .iter()
+        .map(
+            |root| match root.one_file_system || settings.one_file_system {
+                true => device(&root.path),
+                false => None,
+            },
+        )
+        .collect();
+    let seen = Mutex::new(HashSet::new());
+    let started = Instant::now();
+
+    builder.build_parallel().run(|| {
+        let global_ignores = &global_ignores;
+        let devices = &devices;
+        let seen = &seen;
+        let on_hit = &on_hit;
+        let on_dir = &on_dir;
+        Box::new(move |entry| {
+            if settings
+                .timeout
+                .is_some_and(|timeout| started.elapsed() >= timeout)
+            {
+                report.time_out();
+                return WalkState::Quit;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let root = error_path(&e).map_or(0, |path| root_of(roots, path));
+                    if settings.verbose {
+                        report.note(e.to_string());
+                    }
+                    report.error(&roots[root].path, &e);
+                    return WalkState::Continue;
+                }
+            };
+
+            if !entry.file_type().is_some_and(|t| t.is_dir()) {
+                return WalkState::Continue;
+            }
+            // checked against every detector, enabled or not, since vcs
+            // internals never hold checkouts
+            if DETECTORS.iter().any(|d| d.is_internal(entry.file_name())) {
+                return WalkState::Skip;
+            }
+            let root = root_of(roots, entry.path());
+            if roots[root]
+                .timeout
+                .is_some_and(|timeout| started.elapsed() >= timeout)
+            {
+                report.update(&roots[root].path, |problems| problems.timed_out = true);
+                return WalkState::Skip;
+            }
+            if entry.depth() > 0 && entry.path_is_symlink() && !roots[root].follow {
+                return WalkState::Skip;
+            }
+            let crosses_device = devices[root]
+                .is_some_and(|root_device| device(entry.path()).is_some_and(|d| d != root_device));
+            if entry.depth() > 0 && crosses_device {
+                if settings.verbose {
+                    report.note(format!(
+                        "skipped mount point \"{}\"",
+                        entry.path().display()
+                    ));
+                }
+                return WalkState::Skip;
+            }
+            if entry.depth() > 0 && global_ignores[root].matched(entry.path(), true).is_ignore() {
+                return WalkState::Skip;
+            }
+            let descend = || {
+                on_dir(Stamp {
+                    root,
+                    path: entry.path().to_path_buf(),
+                    mtime: entry
+                        .metadata()
+                        .and_then(|meta| Ok(meta.modified()?))
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                });
+                // editing one does not change the directory's time
+                for name in ignore_files(settings) {
+                    let path = entry.path().join(name);
+                    if let Ok(mtime) = path.metadata().and_then(|meta| meta.modified()) {
+                        on_dir(Stamp { root, path, mtime });
+                    }
+                }
+                WalkState::Continue
+            };
+            let max_depth = roots[root].depth.or(settings.depth);
+            let at_max_depth = max_depth.is_some_and(|max| entry.depth() >= max);
+            let detected = settings
+                .detectors
+                .iter()
+                .find_map(|d| d.detect(entry.path()).map(|kind| (d.name(), kind)));
+            let (vcs, kind) = match detected {
+                Some(detected) => detected,
+                None if at_max_depth => return WalkState::Skip,
+                None => return descend(),
+            };
+
+            let wanted = settings
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&kind));
+            let canonical = entry
+                .path()
+                .canonicalize()
+                .unwrap_or(entry.path().to_path_buf());
+            let first_seen = seen.lock().is_ok_and(|mut seen| seen.insert(canonical));
+            let state = match wanted && first_seen {
+                // drops the trailing separator `walk_path` may have added
+                true => on_hit(Repo {
+                    root,
+                    path: entry.path().components().collect(),
+                    vcs,
+                    kind,
+                }),
+                false => WalkState::Continue,
+            };
+            match state {
+                WalkState::Continue
+                    if at_max_depth || kind == Kind::Bare || !roots[root].nested =>
+                {
+                    WalkState::Skip
+                }
+                WalkState::Continue => descend(),
+                state => state,
+            }
+        })
+    });
+}
+
+/// A root that is itself a symlink is always walked, `follow` or not, since
+/// it was put in dirs.txt on purpose. The walker does not resolve links given
+/// as roots, but it does when the path ends with a separator.
+fn walk_path(root: &Root) -> PathBuf {
+    let is_symlink = root
+        .path
+        .symlink_metadata()
+        .is_ok_and(|meta| meta.file_type().is_symlink());
+    if !is_symlink {
+        return root.path.clone();
+    }
+
+    let mut path = root.path.clone().into_os_string();
+    path.push(std::path::MAIN_SEPARATOR_STR);
+    PathBuf::from(path)
+}
+
+/// Id of the filesystem `path` is on, following symlinks; `None` where that
+/// cannot be told, which never counts as crossing into another one.
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    path.metadata().ok().map(|meta| meta.dev())
+}
+
+/// volume serial number on windows, since there are no device ids
+#[cfg(windows)]
+fn device(path: &Path) -> Option<u64> {
+    let handle = winapi_util::Handle::from_path_any(path).ok()?;
+    let info = winapi_util::file::information(&handle).ok()?;
+    Some(info.volume_serial_number())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn device(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// ignore.txt read once per root so its anchored patterns are relative to
+/// that root
+fn global_ignores(roots: &[Root], ignore_txt_path: &Path) -> Vec<Gitignore> {
+    if !ignore_txt_path.is_file() {
+        return roots.iter().map(|_| Gitignore::empty()).collect();
+    }
+
+    roots
+        .iter()
+        .enumerate()
+        .map(|(i, root)| {
+            let mut builder = GitignoreBuilder::new(&root.path);
+            // report bad patterns once, not once per root
+            if let Some(e) = builder.add(ignore_txt_path) {
+                if i == 0 {
+                    elogln(LogLevel::Warn, format!("ignore.txt: {}", e));
+                }
+            }
+            builder.build().unwrap_or_else(|_| Gitignore::empty())
+        })
+        .collect()
+}
+
+/// the path an error happened at, if it says
+fn error_path(e: &ignore::Error) -> Option<&Path> {
+    match e {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err)
+        }
+        _ => None,
+    }
+}
+
+/// index of the deepest root containing `path`
+fn root_of(roots: &[Root], path: &Path) -> usize {
+    roots
+        .iter()
+        .enumerate()
+        .filter(|(_, root)| path.starts_with(&root.path))
+        .max_by_key(|(_, root)| root.path.components().count())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    #[cfg(unix)]
+    use std::os::unix::fs::symlink;
+    use std::time::Duration;
+
+    fn git_repo(path: &Path) {
+        fs::create_dir_all(path.join(".git")).unwrap();
+        fs::write(path.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    }
+
+    /// the paths of the repos under `root`, from it and sorted
+    fn found(root: Root, settings: &Settings) -> Vec<String> {
+        let path = root.path.clone();
+        let mut found: Vec<String> = scan(&[root], settings, &Report::default(), |_| true)
+            .iter()
+            .map(|repo| repo.path.strip_prefix(&path).unwrap().display().to_string())
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn ignore_txt_is_anchored_at_each_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let ignore_txt = dir.path().join("ignore.txt");
+        fs::write(&ignore_txt, "/build\nnode_modules\n").unwrap();
+        let roots = [
+            Root::new(dir.path().join("a")),
+            Root::new(dir.path().join("b")),
+        ];
+        let ignores = global_ignores(&roots, &ignore_txt);
+        let ignored = |root: usize, path: &str| {
+            let path = roots[root].path.join(path);
+            ignores[root].matched(path, true).is_ignore()
+        };
+
+        assert!(ignored(0, "build"));
+        assert!(ignored(1, "build"));
+        assert!(!ignored(0, "x/build"));
+        assert!(ignored(1, "x/node_modules"));
+        assert!(!ignored(0, "src"));
+    }
+
+    #[test]
+    fn no_ignore_txt_ignores_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = [Root::new(dir.path().to_path_buf())];
+        let ignores = global_ignores(&roots, &dir.path().join("ignore.txt"));
+        assert!(!ignores[0]
+            .matched(dir.path().join("build"), true)
+            .is_ignore());
+    }
+
+    #[test]
+    fn sgrignore_skips_what_it_matches_below_it() {
+        let dir = tempfile::tempdir().unwrap();
+        for repo in ["keep", "skip", "group/skip", "group/keep", "other/skip-not"] {
+            git_repo(&dir.path().join(repo));
+        }
+        fs::write(dir.path().join("group").join(".sgrignore"), "skip\n").unwrap();
+        fs::write(dir.path().join(".sgrignore"), "/skip\n").unwrap();
+
+        let root = Root::new(dir.path().to_path_buf());
+        assert_eq!(
+            found(root, &Settings::default()),
+            ["group/keep", "keep", "other/skip-not"]
+        );
+    }
+
+    #[test]
+    fn gitignore_only_counts_with_the_setting() {
+        let dir = tempfile::tempdir().unwrap();
+        git_repo(dir.path());
+        git_repo(&dir.path().join("vendor").join("dep"));
+        fs::write(dir.path().join(".gitignore"), "vendor/\n").unwrap();
+        let mut root = Root::new(dir.path().to_path_buf());
+        root.nested = true;
+
+        assert_eq!(
+            found(root.clone(), &Settings::default()),
+            ["", "vendor/dep"]
+        );
+        let settings = Settings {
+            gitignore: true,
+            ..Settings::default()
+        };
+        assert_eq!(found(root, &settings), [""]);
+    }
+
+    #[test]
+    fn verbose_errors_wait_for_print() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = [Root::new(dir.path().join("missing"))];
+        let notes = |verbose: bool| {
+            let settings = Settings {
+                verbose,
+                ..Settings::default()
+            };
+            let report = Report::default();
+            scan(&roots, &settings, &report, |_| true);
+            report.notes.into_inner().unwrap()
+        };
+
+        assert!(notes(false).is_empty());
+        let notes = notes(true);
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("missing"));
+    }
+
+    #[cfg(unix)]
+    fn follow(path: PathBuf) -> Root {
+        Root {
+            follow: true,
+            ..Root::new(path)
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn links_are_only_walked_with_follow() {
+        let dir = tempfile::tempdir().unwrap();
+        git_repo(&dir.path().join("outside/repo"));
+        git_repo(&dir.path().join("root/inside"));
+        symlink(dir.path().join("outside"), dir.path().join("root/link")).unwrap();
+        let root = dir.path().join("root");
+        let settings = Settings::default();
+
+        assert_eq!(found(Root::new(root.clone()), &settings), ["inside"]);
+        assert_eq!(found(follow(root), &settings), ["inside", "link/repo"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn loops_are_not_walked() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        git_repo(&root.join("a/repo"));
+        symlink(&root, root.join("a/back-to-root")).unwrap();
+        symlink("..", root.join("a/repo/up")).unwrap();
+        let report = Report::default();
+
+        let found = scan(&[follow(root)], &Settings::default(), &report, |_| true);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].path.ends_with("a/repo"));
+        // loops are expected with `follow`, so they are no problem
+        assert!(report.roots.into_inner().unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn a_repo_behind_two_links_is_found_once() {
+        let dir = tempfile::tempdir().unwrap();
+        git_repo(&dir.path().join("outside/repo"));
+        fs::create_dir(dir.path().join("root")).unwrap();
+        for link in ["root/one", "root/two"] {
+            symlink(dir.path().join("outside"), dir.path().join(link)).unwrap();
+        }
+
+        let found = found(follow(dir.path().join("root")), &Settings::default());
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert!(found[0].ends_with("/repo"));
+    }
+
+    /// timed out, permission denied and other errors of each root
+    fn problems(report: Report) -> Vec<(PathBuf, bool, usize, usize)> {
+        report
+            .roots
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|(path, p)| (path, p.timed_out, p.permission_denied, p.io_errors))
+            .collect()
+    }
+
+    #[test]
+    fn report_counts_errors_by_root() {
+        let io = |kind| ignore::Error::Io(std::io::Error::from(kind));
+        let report = Report::default();
+        let (a, b) = (Path::new("/a"), Path::new("/b"));
+        report.error(a, &io(ErrorKind::PermissionDenied));
+        report.error(b, &io(ErrorKind::Other));
+        report.error(a, &io(ErrorKind::PermissionDenied));
+        report.error(a, &io(ErrorKind::NotFound));
+        // not an I/O error, like a bad ignore pattern
+        report.error(
+            b,
+            &ignore::Error::Glob {
+                glob: None,
+                err: "bad".to_string(),
+            },
+        );
+
+        assert!(!report.timed_out());
+        assert_eq!(
+            problems(report),
+            [(a.into(), false, 2, 1), (b.into(), false, 0, 1)]
+        );
+    }
+
+    #[test]
+    fn report_timeouts() {
+        let report = Report::default();
+        report.update(Path::new("/a"), |problems| problems.timed_out = true);
+        assert!(report.timed_out());
+
+        let report = Report::default();
+        report.time_out();
+        assert!(report.timed_out());
+        assert!(problems(report).is_empty());
+    }
+
+    #[test]
+    fn scan_timeouts_end_up_in_the_report() {
+        let dir = tempfile::tempdir().unwrap();
+        git_repo(&dir.path().join("a/repo"));
+        git_repo(&dir.path().join("b/repo"));
+
+        let report = Report::default();
+        let settings = Settings {
+            timeout: Some(Duration::ZERO),
+            ..Settings::default()
+        };
+        let found = scan(
+            &[Root::new(dir.path().join("a"))],
+            &settings,
+            &report,
+            |_| true,
+        );
+        assert!(found.is_empty());
+        assert!(report.timed_out());
+
+        let report = Report::default();
+        let roots = [
+            Root {
+                timeout: Some(Duration::ZERO),
+                ..Root::new(dir.path().join("a"))
+            },
+            Root::new(dir.path().join("b")),
+        ];
+        let found = scan(&roots, &Settings::default(), &report, |_| true);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].path.starts_with(dir.path().join("b")));
+        assert!(report.timed_out());
+        assert_eq!(problems(report), [(dir.path().join("a"), true, 0, 0)]);
+    }
+}
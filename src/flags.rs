@@ -0,0 +1,161 @@
+// standard library
+use std::time::Duration;
+
+// own
+use crate::action::Action;
+use crate::config;
+use crate::detect::{self, Kind, RepoDetector};
+use crate::format::Format;
+use crate::selector::Selector;
+use crate::utils::{format_log, LogLevel};
+
+/// options given anywhere on the command line as `--name value` or
+/// `--name=value`; they override dirs.txt and config.txt for one call
+#[derive(Default)]
+pub struct Flags {
+    /// max depth to search every root to
+    pub depth: Option<usize>,
+    /// only list repos of these kinds
+    pub kinds: Option<Vec<Kind>>,
+    /// detectors to use instead of the `vcs` setting
+    pub detectors: Option<Vec<&'static dyn RepoDetector>>,
+    /// report what the walk skipped and why
+    pub verbose: bool,
+    /// time budget for the whole search instead of the `timeout` setting
+    pub timeout: Option<Duration>,
+    /// picker to use instead of the `selector` setting
+    pub selector: Option<Selector>,
+    /// pick several repos in `run`
+    pub multi: bool,
+    /// end each printed path with a NUL instead of a newline
+    pub print0: bool,
+    /// print repos and dirs.txt entries as records for scripts
+    pub format: Option<Format>,
+    /// what to do with the picked repos instead of the `action` setting
+    pub action: Option<Action>,
+    /// name of the shell function `init` prints
+    pub function: Option<String>,
+    /// `init` also binds keys to jump to a repo and to insert its path
+    pub bind: bool,
+    /// key `init` binds to jump to a repo, like `ctrl-g`
+    pub jump_key: Option<String>,
+    /// key `init` binds to insert a repo's path at the cursor, like `alt-g`
+    pub insert_key: Option<String>,
+    /// everything after `--`, passed on to the selector as is after the
+    /// `selector-options` setting
+    pub selector_options: Vec<String>,
+}
+
+impl Flags {
+    /// splits flags out of `args`, returning the rest in order
+    pub fn parse(args: &[String]) -> Result<(Flags, Vec<&str>), String> {
+        let mut flags = Flags::default();
+        let mut rest = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                flags.selector_options = args.by_ref().cloned().collect();
+                break;
+            }
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => {
+                    rest.push(arg.as_str());
+                    continue;
+                }
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (flag, None),
+            };
+
+            match name {
+                "depth" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    flags.depth = Some(parse_usize(name, value)?);
+                }
+                "kind" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.kinds = Some(Kind::parse_list(value)?);
+                }
+                "vcs" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.detectors = Some(detect::parse_list(value)?);
+                }
+                "timeout" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.timeout = Some(config::parse_secs(value).ok_or(format_log(
+                        LogLevel::Error,
+                        format!("'--{}' expects seconds; got '{}'", name, value),
+                    ))?);
+                }
+                "selector" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.selector = Some(Selector::parse(value)?);
+                }
+                "function" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.function = Some(value.to_string());
+                }
+                "action" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.action = Some(Action::parse(value)?);
+                }
+                "format" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.format = Some(Format::parse(value)?);
+                }
+                "jump-key" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.jump_key = Some(value.to_string());
+                }
+                "insert-key" => {
+                    let value = value.or_else(|| args.next().map(|v| v.as_str()));
+                    let value = value.ok_or(missing_value(name))?;
+                    flags.insert_key = Some(value.to_string());
+                }
+                "verbose" if value.is_none() => flags.verbose = true,
+                "multi" if value.is_none() => flags.multi = true,
+                "print0" if value.is_none() => flags.print0 = true,
+                "bind" if value.is_none() => flags.bind = true,
+                _ => {
+                    return Err(format_log(
+                        LogLevel::Error,
+                        format!("unknown flag '--{}'", name),
+                    ))
+                }
+            }
+        }
+
+        Ok((flags, rest))
+    }
+
+    /// whether repos are searched differently than dirs.txt and config.txt
+    /// say, so an index built from them does not apply
+    pub fn overrides_scan(&self) -> bool {
+        self.depth.is_some() || self.kinds.is_some() || self.detectors.is_some()
+    }
+}
+
+fn missing_value(name: &str) -> String {
+    format_log(LogLevel::Error, format!("missing value for '--{}'", name))
+}
+
+fn parse_usize(name: &str, value: Option<&str>) -> Result<usize, String> {
+    let value = value.ok_or(missing_value(name))?;
+    value.parse().map_err(|_| {
+        format_log(
+            LogLevel::Error,
+            format!("'--{}' expects a number; got '{}'", name, value),
+        )
+    })
+}
@@ -0,0 +1,238 @@
+// standard library
+use std::path::Path;
+
+// own
+use crate::config::Root;
+use crate::discover::Repo;
+use crate::flags::Flags;
+use crate::utils::{format_log, LogLevel};
+
+/// how repos and dirs.txt entries are printed for scripts to read
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    /// only the path
+    Plain,
+    /// the fields of a record separated by tabs
+    Tsv,
+    /// one JSON object per record
+    Json,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name {
+            "plain" => Ok(Format::Plain),
+            "tsv" => Ok(Format::Tsv),
+            "json" => Ok(Format::Json),
+            _ => Err(format_log(
+                LogLevel::Error,
+                format!("unknown format '{}'; expected json, tsv or plain", name),
+            )),
+        }
+    }
+}
+
+/// `repo` as `path`, `root`, `line`, `vcs` and `kind`, in that order, with
+/// the vcs and kind it was found as. The root is the one in `roots` it was
+/// found under and the line is the root's line in dirs.txt. Fields that are
+/// not known are null in JSON and empty in TSV.
+pub fn repo(repo: &Repo, roots: &[Root], format: Format) -> String {
+    let path = repo.path.display().to_string();
+    if format == Format::Plain {
+        return path;
+    }
+    let root = roots.get(repo.root);
+    let root_path = root.map(|root| root.path.display().to_string());
+    let line = root.map(|root| root.line.to_string());
+    let (vcs, kind) = (repo.vcs, repo.kind.name());
+    match format {
+        Format::Json => format!(
+            "{{\"path\":{},\"root\":{},\"line\":{},\"vcs\":{},\"kind\":{}}}",
+            json_string(&path),
+            root_path.as_deref().map_or("null".to_string(), json_string),
+            line.as_deref().unwrap_or("null"),
+            json_string(vcs),
+            json_string(kind),
+        ),
+        _ => [
+            path,
+            root_path.unwrap_or_default(),
+            line.unwrap_or_default(),
+            vcs.to_string(),
+            kind.to_string(),
+        ]
+        .join("\t"),
+    }
+}
+
+/// A repo in history.txt as `path`, `rank`, `count` and `last`, in that
+/// order, with `last` in seconds since 1970.
+pub fn history(path: &Path, rank: u64, count: u64, last: u64, format: Format) -> String {
+    let path = path.display().to_string();
+    match format {
+        Format::Plain => path,
+        Format::Tsv => format!("{}\t{}\t{}\t{}", path, rank, count, last),
+        Format::Json => format!(
+            "{{\"path\":{},\"rank\":{},\"count\":{},\"last\":{}}}",
+            json_string(&path),
+            rank,
+            count,
+            last
+        ),
+    }
+}
+
+/// Line `line_num` of dirs.txt as `line`, `root` and `options`, in that
+/// order, or only the root's path for plain output.
+pub fn entry(line_num: usize, line: &str, format: Format) -> String {
+    let (root, options) = match line.split_once('|') {
+        Some((root, options)) => (root.trim(), options.trim()),
+        None => (line.trim(), ""),
+    };
+    match format {
+        Format::Plain => root.to_string(),
+        Format::Tsv => format!("{}\t{}\t{}", line_num, root, options),
+        Format::Json => format!(
+            "{{\"line\":{},\"root\":{},\"options\":{}}}",
+            line_num,
+            json_string(root),
+            json_string(options)
+        ),
+    }
+}
+
+/// `records` one per line, or each ended by a NUL with `--print0`
+pub fn join(records: &[String], flags: &Flags) -> String {
+    match flags.print0 {
+        true => records
+            .iter()
+            .map(|record| format!("{}\0", record))
+            .collect(),
+        false => records.join("\n"),
+    }
+}
+
+/// `s` quoted as a JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detect::Kind;
+    use std::path::PathBuf;
+
+    fn sugar(root: usize) -> Repo {
+        Repo {
+            root,
+            path: PathBuf::from("/work/sugar"),
+            vcs: "git",
+            kind: Kind::Worktree,
+        }
+    }
+
+    fn work() -> Vec<Root> {
+        let mut root = Root::new(PathBuf::from("/work"));
+        root.line = 3;
+        vec![root]
+    }
+
+    #[test]
+    fn json_escapes() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(json_string("C:\\Users"), "\"C:\\\\Users\"");
+        assert_eq!(json_string("a\nb\rc\td"), "\"a\\nb\\rc\\td\"");
+        assert_eq!(json_string("bell\u{7}"), "\"bell\\u0007\"");
+        assert_eq!(json_string("\u{1b}[0m"), "\"\\u001b[0m\"");
+        assert_eq!(json_string("grün"), "\"grün\"");
+    }
+
+    #[test]
+    fn parse_formats() {
+        assert!(Format::parse("json").unwrap() == Format::Json);
+        assert!(Format::parse("tsv").unwrap() == Format::Tsv);
+        assert!(Format::parse("plain").unwrap() == Format::Plain);
+        assert!(Format::parse("JSON").is_err());
+    }
+
+    #[test]
+    fn repo_records() {
+        let roots = work();
+        assert_eq!(repo(&sugar(0), &roots, Format::Plain), "/work/sugar");
+        assert_eq!(
+            repo(&sugar(0), &roots, Format::Tsv),
+            "/work/sugar\t/work\t3\tgit\tworktree"
+        );
+        assert_eq!(
+            repo(&sugar(0), &roots, Format::Json),
+            "{\"path\":\"/work/sugar\",\"root\":\"/work\",\"line\":3,\"vcs\":\"git\",\"kind\":\"worktree\"}"
+        );
+    }
+
+    #[test]
+    fn repo_without_its_root() {
+        assert_eq!(
+            repo(&sugar(1), &work(), Format::Tsv),
+            "/work/sugar\t\t\tgit\tworktree"
+        );
+        assert_eq!(
+            repo(&sugar(1), &work(), Format::Json),
+            "{\"path\":\"/work/sugar\",\"root\":null,\"line\":null,\"vcs\":\"git\",\"kind\":\"worktree\"}"
+        );
+    }
+
+    #[test]
+    fn entry_records() {
+        let line = " /work | nested depth=2 ";
+        assert_eq!(entry(3, line, Format::Plain), "/work");
+        assert_eq!(entry(3, line, Format::Tsv), "3\t/work\tnested depth=2");
+        assert_eq!(
+            entry(3, line, Format::Json),
+            "{\"line\":3,\"root\":\"/work\",\"options\":\"nested depth=2\"}"
+        );
+        assert_eq!(entry(1, "/home", Format::Tsv), "1\t/home\t");
+    }
+
+    #[test]
+    fn history_records() {
+        let path = Path::new("/work/sugar");
+        assert_eq!(
+            history(path, 40, 5, 1700000000, Format::Plain),
+            "/work/sugar"
+        );
+        assert_eq!(
+            history(path, 40, 5, 1700000000, Format::Tsv),
+            "/work/sugar\t40\t5\t1700000000"
+        );
+        assert_eq!(
+            history(path, 40, 5, 1700000000, Format::Json),
+            "{\"path\":\"/work/sugar\",\"rank\":40,\"count\":5,\"last\":1700000000}"
+        );
+    }
+
+    #[test]
+    fn join_records() {
+        let records = ["a".to_string(), "b c".to_string()];
+        let mut flags = Flags::default();
+        assert_eq!(join(&records, &flags), "a\nb c");
+        assert_eq!(join(&[], &flags), "");
+        flags.print0 = true;
+        assert_eq!(join(&records, &flags), "a\0b c\0");
+    }
+}
@@ -0,0 +1,160 @@
+/// how well a query matched a line, and where
+pub struct Match {
+    pub score: i64,
+    /// indices of the matched chars in the line, ascending
+    pub positions: Vec<usize>,
+}
+
+/// points for each matched char
+const MATCH: i64 = 16;
+/// extra for a char right after the previous matched one
+const CONSECUTIVE: i64 = 8;
+/// extra for a char starting a word, like right after a `/`
+const BOUNDARY: i64 = 8;
+/// taken off for every char skipped between two matched ones
+const GAP: i64 = 1;
+
+/// Matches every space separated term of `query` against `line` as a
+/// subsequence, like fzf. Terms are case insensitive unless they have an
+/// uppercase char. An empty query matches everything with a score of 0.
+pub fn fuzzy_match(query: &str, line: &str) -> Option<Match> {
+    let line: Vec<char> = line.chars().collect();
+    let mut result = Match {
+        score: 0,
+        positions: Vec::new(),
+    };
+
+    for term in query.split_whitespace() {
+        let term_match = match_term(term, &line)?;
+        result.score += term_match.score;
+        result.positions.extend(term_match.positions);
+    }
+    result.positions.sort_unstable();
+    result.positions.dedup();
+    Some(result)
+}
+
+/// Finds the first place `term` matches as a subsequence, then walks back
+/// from where it ended so the match is as short as it can be there.
+fn match_term(term: &str, line: &[char]) -> Option<Match> {
+    let case_sensitive = term.chars().any(char::is_uppercase);
+    let fold = |c: char| match case_sensitive {
+        true => c,
+        false => c.to_lowercase().next().unwrap_or(c),
+    };
+    let term: Vec<char> = term.chars().map(fold).collect();
+
+    let mut t = 0;
+    let mut end = None;
+    for (i, &c) in line.iter().enumerate() {
+        if fold(c) == term[t] {
+            t += 1;
+            if t == term.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut positions = Vec::with_capacity(term.len());
+    let mut t = term.len();
+    for i in (0..=end).rev() {
+        if fold(line[i]) == term[t - 1] {
+            positions.push(i);
+            t -= 1;
+            if t == 0 {
+                break;
+            }
+        }
+    }
+    positions.reverse();
+
+    let mut score = 0;
+    for (n, &i) in positions.iter().enumerate() {
+        score += MATCH;
+        if i == 0 || is_separator(line[i - 1]) {
+            score += BOUNDARY;
+        }
+        if n > 0 {
+            let previous = positions[n - 1];
+            match i - previous {
+                1 => score += CONSECUTIVE,
+                gap => score -= GAP * (gap as i64 - 1),
+            }
+        }
+    }
+    Some(Match { score, positions })
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '/' | '\\' | '-' | '_' | '.' | ' ' | '\t')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(query: &str, line: &str) -> Option<Vec<usize>> {
+        fuzzy_match(query, line).map(|m| m.positions)
+    }
+
+    fn score(query: &str, line: &str) -> i64 {
+        fuzzy_match(query, line).unwrap().score
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        let m = fuzzy_match("", "anything").unwrap();
+        assert_eq!(m.score, 0);
+        assert!(m.positions.is_empty());
+        assert_eq!(score("  ", "anything"), 0);
+    }
+
+    #[test]
+    fn subsequence() {
+        assert_eq!(positions("sgr", "sugar"), Some(vec![0, 2, 4]));
+        assert_eq!(positions("rgs", "sugar"), None);
+        assert_eq!(positions("sugary", "sugar"), None);
+    }
+
+    #[test]
+    fn smart_case() {
+        assert_eq!(positions("src", "SRC/main.rs"), Some(vec![0, 1, 2]));
+        assert_eq!(positions("Src", "src/main.rs"), None);
+        assert_eq!(positions("Src", "Src/main.rs"), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn every_term_has_to_match() {
+        assert_eq!(
+            positions("main src", "src/main.rs"),
+            Some(vec![0, 1, 2, 4, 5, 6, 7])
+        );
+        assert_eq!(positions("main lib", "src/main.rs"), None);
+        assert_eq!(
+            score("src main", "src/main.rs"),
+            score("src", "src/main.rs") + score("main", "src/main.rs")
+        );
+    }
+
+    #[test]
+    fn shortest_match_where_it_ends() {
+        // the first `a` would make a longer match ending at the same `b`
+        assert_eq!(positions("ab", "a-xab"), Some(vec![3, 4]));
+    }
+
+    #[test]
+    fn consecutive_and_boundaries_score_more() {
+        assert!(score("main", "src/main.rs") > score("main", "src/mxaxixn.rs"));
+        assert!(score("m", "src/main.rs") > score("a", "src/main.rs"));
+        assert!(score("sm", "src/main") > score("sm", "srcxmain"));
+        assert!(score("ab", "a___b") < score("ab", "a_b"));
+    }
+
+    #[test]
+    fn positions_are_chars_not_bytes() {
+        assert_eq!(positions("ü", "grün"), Some(vec![2]));
+        assert_eq!(positions("n", "grün"), Some(vec![3]));
+    }
+}
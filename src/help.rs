@@ -1,4 +1,7 @@
-use crate::{add, list, remove, to_command, utils::StringExt, version};
+use crate::{
+    add, cache, completions, daemon, history, init, list, preview, query, remove, run, to_command,
+    utils::StringExt, version,
+};
 
 pub fn execute(arg: Option<&str>) -> Result<(), String> {
     let command = match arg {
@@ -33,9 +36,17 @@ pub fn execute(arg: Option<&str>) -> Result<(), String> {
                 "<optional_arg>".to_string().italic()
             );
             println!("{}: ", "Optional Commands".to_string().bold().underline());
+            run::help(false);
+            query::help(false);
             add::help(false);
             remove::help(false);
             list::help(false);
+            cache::help(false);
+            daemon::help(false);
+            history::help(false);
+            preview::help(false);
+            init::help(false);
+            completions::help(false);
             help(false);
             version::help(false);
         }
@@ -0,0 +1,392 @@
+// standard library
+use std::fs::{remove_file, rename, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// own
+use crate::action;
+use crate::config::{self, Root, Settings};
+use crate::discover::{self, Repo};
+use crate::flags::Flags;
+use crate::format::{self, Format};
+use crate::utils::{format_log, read_lines, LogLevel, StringExt};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// a repo jumped to before
+pub struct Entry {
+    pub path: PathBuf,
+    /// how many times it was jumped to
+    pub count: u64,
+    /// when it was last jumped to
+    pub last: SystemTime,
+}
+
+impl Entry {
+    /// how often it was jumped to, weighted by how recently, like z does
+    pub fn frecency(&self, now: SystemTime) -> u64 {
+        let age = now.duration_since(self.last).unwrap_or_default().as_secs();
+        let weight = match age {
+            age if age < HOUR => 16,
+            age if age < DAY => 8,
+            age if age < WEEK => 2,
+            _ => 1,
+        };
+        self.count * weight
+    }
+}
+
+fn history_txt_path() -> Result<PathBuf, String> {
+    Ok(config::config_dir()?.join("history.txt"))
+}
+
+pub fn load() -> Result<Vec<Entry>, String> {
+    load_from(&history_txt_path()?)
+}
+
+/// Reads history.txt, one `count<TAB>last used<TAB>path` line per repo with
+/// the time in seconds since 1970. Lines that do not fit are skipped so a
+/// bad edit only loses that line.
+fn load_from(history_txt_path: &Path) -> Result<Vec<Entry>, String> {
+    let file = match OpenOptions::new().read(true).open(history_txt_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"{:?}\": {}", history_txt_path, e),
+            ))
+        }
+    };
+
+    Ok(read_lines(BufReader::new(file))
+        .filter_map(
+            |line| match line.splitn(3, '\t').collect::<Vec<&str>>()[..] {
+                [count, last, path] => Some(Entry {
+                    path: PathBuf::from(path),
+                    count: count.trim().parse().ok()?,
+                    last: SystemTime::UNIX_EPOCH + Duration::from_secs(last.trim().parse().ok()?),
+                }),
+                _ => None,
+            },
+        )
+        .collect())
+}
+
+/// writes to temp_history.txt first so a failed write leaves history.txt as
+/// it was
+fn save_to(history_txt_path: &Path, entries: &[Entry]) -> Result<(), String> {
+    let temp_file_path = history_txt_path.with_file_name("temp_history.txt");
+    let temp_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&temp_file_path)
+        .map_err(|e| {
+            format_log(
+                LogLevel::Error,
+                format!("Failed to open file \"temp_history.txt\": {}", e),
+            )
+        })?;
+
+    let mut writer = BufWriter::new(temp_file);
+    let mut write = || -> std::io::Result<()> {
+        for entry in entries {
+            let last = entry
+                .last
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            writeln!(
+                writer,
+                "{}\t{}\t{}",
+                entry.count,
+                last,
+                entry.path.display()
+            )?;
+        }
+        writer.flush()
+    };
+    write().map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to write to file \"temp_history.txt\": {}", e),
+        )
+    })?;
+
+    rename(&temp_file_path, history_txt_path).map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!(
+                "Failed to rename \"temp_history.txt\" to \"history.txt\": {}",
+                e
+            ),
+        )
+    })
+}
+
+/// counts one more jump to `path`
+pub fn record(path: &Path) -> Result<(), String> {
+    record_in(&history_txt_path()?, path, SystemTime::now())
+}
+
+fn record_in(history_txt_path: &Path, path: &Path, now: SystemTime) -> Result<(), String> {
+    let mut entries = load_from(history_txt_path)?;
+    match entries.iter_mut().find(|entry| entry.path == path) {
+        Some(entry) => {
+            entry.count += 1;
+            entry.last = now;
+        }
+        None => entries.push(Entry {
+            path: path.to_path_buf(),
+            count: 1,
+            last: now,
+        }),
+    }
+    save_to(history_txt_path, &entries)
+}
+
+/// removes the repos that no longer exist, returning how many
+fn prune(history_txt_path: &Path) -> Result<usize, String> {
+    let mut entries = load_from(history_txt_path)?;
+    let before = entries.len();
+    entries.retain(|entry| entry.path.is_dir());
+    save_to(history_txt_path, &entries)?;
+    Ok(before - entries.len())
+}
+
+/// repos from history that are still repos under `roots`, most frecent first
+pub fn ranked(roots: &[Root], settings: &Settings) -> Vec<Repo> {
+    let mut entries = load().unwrap_or_default();
+    let now = SystemTime::now();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.frecency(now)));
+    entries
+        .iter()
+        .filter_map(|entry| discover::repo_at(roots, settings, &entry.path))
+        .collect()
+}
+
+/// every entry of history.txt, most frecent first
+fn by_rank(now: SystemTime) -> Result<Vec<Entry>, String> {
+    let mut entries = load()?;
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.frecency(now)));
+    Ok(entries)
+}
+
+/// `show` as records for scripts, for `--format` and `--print0`
+pub fn records(arg: Option<&str>, flags: &Flags) -> Result<String, String> {
+    if arg.is_some_and(|arg| arg != "show") {
+        return Err(format_log(
+            LogLevel::Error,
+            "'--format' and '--print0' only go with 'history show'".to_string(),
+        ));
+    }
+    let format = flags.format.unwrap_or(Format::Plain);
+    let now = SystemTime::now();
+    let records: Vec<String> = by_rank(now)?
+        .iter()
+        .map(|entry| {
+            let last = entry.last.duration_since(SystemTime::UNIX_EPOCH);
+            format::history(
+                &entry.path,
+                entry.frecency(now),
+                entry.count,
+                last.unwrap_or_default().as_secs(),
+                format,
+            )
+        })
+        .collect();
+    Ok(format::join(&records, flags))
+}
+
+pub fn execute(arg: Option<&str>, _flags: &Flags) -> Result<(), String> {
+    match arg.unwrap_or("show") {
+        "show" => {
+            let now = SystemTime::now();
+            for entry in by_rank(now)? {
+                println!(
+                    "{}{}{}",
+                    entry.frecency(now).to_string().pad_right(8),
+                    entry.count.to_string().pad_right(8),
+                    entry.path.display()
+                );
+            }
+        }
+        "edit" => action::edit(&[history_txt_path()?.display().to_string()])?,
+        "prune" => {
+            let removed = prune(&history_txt_path()?)?;
+            println!(
+                "removed {} repo(s) that no longer exist from history.txt",
+                removed
+            );
+        }
+        "clear" => {
+            let history_txt_path = history_txt_path()?;
+            match remove_file(&history_txt_path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(format_log(
+                        LogLevel::Error,
+                        format!("Failed to remove file \"history.txt\": {}", e),
+                    ))
+                }
+            }
+            println!("cleared {}", history_txt_path.display());
+        }
+        arg => {
+            return Err(format_log(
+                LogLevel::Error,
+                format!(
+                    "unknown arg '{}' for 'history'; expected show, edit, prune or clear",
+                    arg
+                ),
+            ))
+        }
+    }
+    Ok(())
+}
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "history"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "history".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!(
+        "{}shows, edits or prunes the repos jumped to before,",
+        title
+    );
+    println!(
+        "{}which are listed first in the picker\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!(
+            "{}Repos jumped to more often and more recently rank higher",
+            "".to_string().pad_right(15).fill_left(2)
+        );
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!("{}", "sgr history".to_string().fill_left(17));
+        println!("{}", "sgr history show".to_string().fill_left(17));
+        println!(
+            "{}",
+            "sugar history show --format json".to_string().fill_left(17)
+        );
+        println!("{}", "sgr history edit".to_string().fill_left(17));
+        println!("{}", "sgr history prune".to_string().fill_left(17));
+        println!("{}", "sgr history clear".to_string().fill_left(17));
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. \"show\" prints the rank, the jump count, then the path of each repo");
+        println!("  2. \"edit\" opens history.txt in $VISUAL or $EDITOR");
+        println!("  3. \"prune\" removes repos that no longer exist");
+        println!("  4. Set \"frecency = false\" in config.txt to list repos in the order found");
+        println!("  5. \"--format json|tsv|plain\" or \"--print0\" print the path, rank, count");
+        println!("     and last jump of each as records for scripts instead");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn entry(count: u64, last: SystemTime) -> Entry {
+        Entry {
+            path: PathBuf::from("/work/sugar"),
+            count,
+            last,
+        }
+    }
+
+    /// count and path of every entry, in file order
+    fn counts(history_txt: &Path) -> Vec<(u64, PathBuf)> {
+        load_from(history_txt)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.count, entry.path))
+            .collect()
+    }
+
+    #[test]
+    fn frecency_weighs_recent_jumps_more() {
+        let now = at(10 * WEEK);
+        assert_eq!(entry(3, now).frecency(now), 48);
+        assert_eq!(entry(3, at(10 * WEEK - HOUR)).frecency(now), 24);
+        assert_eq!(entry(3, at(10 * WEEK - DAY)).frecency(now), 6);
+        assert_eq!(entry(3, at(9 * WEEK)).frecency(now), 3);
+        // a clock set back counts as just now
+        assert_eq!(entry(3, at(11 * WEEK)).frecency(now), 48);
+    }
+
+    #[test]
+    fn record_counts_jumps() {
+        let dir = tempfile::tempdir().unwrap();
+        let history_txt = dir.path().join("history.txt");
+        let (a, b) = (Path::new("/work/a"), Path::new("/work/b c"));
+        record_in(&history_txt, a, at(100)).unwrap();
+        record_in(&history_txt, b, at(200)).unwrap();
+        record_in(&history_txt, a, at(300)).unwrap();
+
+        assert_eq!(counts(&history_txt), [(2, a.into()), (1, b.into())]);
+        let entries = load_from(&history_txt).unwrap();
+        assert_eq!(entries[0].last, at(300));
+        assert_eq!(entries[1].last, at(200));
+        assert!(!dir.path().join("temp_history.txt").exists());
+    }
+
+    #[test]
+    fn load_skips_lines_that_do_not_fit() {
+        let dir = tempfile::tempdir().unwrap();
+        let history_txt = dir.path().join("history.txt");
+        fs::write(
+            &history_txt,
+            "2\t100\t/work/a\n\
+             /work/no-count\n\
+             x\t100\t/work/bad-count\n\
+             1\tyesterday\t/work/bad-time\n\
+             \n\
+             3\t200\t/work/tab\tin name\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            counts(&history_txt),
+            [(2, "/work/a".into()), (3, "/work/tab\tin name".into())]
+        );
+    }
+
+    #[test]
+    fn no_history_txt_is_no_history() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_from(&dir.path().join("history.txt"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn prune_removes_repos_that_are_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let history_txt = dir.path().join("history.txt");
+        let (kept, gone) = (dir.path().join("kept"), dir.path().join("gone"));
+        fs::create_dir(&kept).unwrap();
+        record_in(&history_txt, &gone, at(100)).unwrap();
+        record_in(&history_txt, &kept, at(100)).unwrap();
+
+        assert_eq!(prune(&history_txt).unwrap(), 1);
+        assert_eq!(counts(&history_txt), [(1, kept)]);
+        assert_eq!(prune(&history_txt).unwrap(), 0);
+    }
+}
@@ -0,0 +1,498 @@
+// own
+use crate::flags::Flags;
+use crate::key::Key;
+use crate::utils::{format_log, posix_quote, LogLevel, StringExt};
+use crate::COMMANDS;
+
+/// commands that print something other than a path to cd to, so the shell
+/// function runs them as is
+fn pass_through() -> Vec<&'static str> {
+    COMMANDS
+        .into_iter()
+        .filter(|command| !["run", "query"].contains(command))
+        .collect()
+}
+
+/// a shell `init` prints a function for
+#[derive(Clone, Copy)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+    Pwsh,
+    Cmd,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 6] = [
+        Shell::Bash,
+        Shell::Zsh,
+        Shell::Fish,
+        Shell::Nushell,
+        Shell::Pwsh,
+        Shell::Cmd,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Nushell => "nushell",
+            Shell::Pwsh => "pwsh",
+            Shell::Cmd => "cmd",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Shell, String> {
+        Shell::ALL
+            .into_iter()
+            .find(|shell| shell.name() == name)
+            .ok_or(format_log(
+                LogLevel::Error,
+                format!(
+                    "unknown shell '{}'; expected bash, zsh, fish, nushell, pwsh or cmd",
+                    name
+                ),
+            ))
+    }
+
+    /// `path` as a literal the shell runs as a program
+    pub fn quote(&self, path: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => posix_quote(path),
+            Shell::Fish => format!("'{}'", path.replace('\\', "\\\\").replace('\'', "\\'")),
+            // backticks take a path as is, without escapes
+            Shell::Nushell => format!("`{}`", path),
+            Shell::Pwsh => format!("'{}'", path.replace('\'', "''")),
+            Shell::Cmd => format!("\"{}\"", path),
+        }
+    }
+
+    /// the key bindings, if the shell's line editor can run a function
+    fn widgets(&self) -> Option<&'static str> {
+        match self {
+            Shell::Bash => Some(BASH_WIDGETS),
+            Shell::Zsh => Some(ZSH_WIDGETS),
+            Shell::Fish => Some(FISH_WIDGETS),
+            Shell::Nushell | Shell::Pwsh | Shell::Cmd => None,
+        }
+    }
+
+    fn template(&self) -> &'static str {
+        match self {
+            Shell::Bash | Shell::Zsh => POSIX,
+            Shell::Fish => FISH,
+            Shell::Nushell => NUSHELL,
+            Shell::Pwsh => PWSH,
+            Shell::Cmd => CMD,
+        }
+    }
+}
+
+/// `key` as `bindkey`, `bind -x` or fish's `bind` write it
+fn notation(key: &Key, shell: Shell) -> String {
+    let (ctrl, alt) = match shell {
+        Shell::Zsh => (format!("^{}", key.key.to_ascii_uppercase()), "^["),
+        Shell::Bash => (format!("\\C-{}", key.key), "\\e"),
+        _ => (format!("\\c{}", key.key), "\\e"),
+    };
+    let base = match key.ctrl {
+        true => ctrl,
+        false => key.key.to_string(),
+    };
+    match key.alt {
+        true => format!("{}{}", alt, base),
+        false => base,
+    }
+}
+
+/// The shell function that runs sgr and cds to the chosen repo, for the
+/// shell's rc file to evaluate.
+pub fn execute(arg: Option<&str>, flags: &Flags) -> Result<String, String> {
+    let shell = Shell::parse(arg.ok_or(format_log(
+        LogLevel::Error,
+        "missing shell for 'init'; expected bash, zsh, fish, nushell, pwsh or cmd".to_string(),
+    ))?)?;
+    let name = flags.function.as_deref().unwrap_or("sgr");
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format_log(
+            LogLevel::Error,
+            format!(
+                "'--function' must be letters, digits, '_' or '-'; got '{}'",
+                name
+            ),
+        ));
+    }
+    let exe = std::env::current_exe().map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to get sgr.exe path: {}", e),
+        )
+    })?;
+
+    let mut script = render(shell, shell.template(), name, &exe.display().to_string());
+    if flags.bind || flags.jump_key.is_some() || flags.insert_key.is_some() {
+        let widgets = shell.widgets().ok_or(format_log(
+            LogLevel::Error,
+            "key bindings are only for bash, zsh and fish".to_string(),
+        ))?;
+        let jump = Key::parse(
+            "'--jump-key'",
+            flags.jump_key.as_deref().unwrap_or("ctrl-g"),
+        )?;
+        let insert = Key::parse(
+            "'--insert-key'",
+            flags.insert_key.as_deref().unwrap_or("alt-g"),
+        )?;
+        script += &render(shell, widgets, name, &exe.display().to_string())
+            .replace("{jump_key}", &jump.name())
+            .replace("{insert_key}", &insert.name())
+            .replace("{jump}", &notation(&jump, shell))
+            .replace("{insert}", &notation(&insert, shell));
+    }
+
+    // printed with a newline after it
+    Ok(script.trim_end().to_string())
+}
+
+fn render(shell: Shell, template: &str, name: &str, exe: &str) -> String {
+    let pass_through = pass_through();
+    let commands = match shell {
+        Shell::Bash | Shell::Zsh => pass_through.join("|"),
+        Shell::Nushell | Shell::Pwsh => pass_through
+            .iter()
+            .map(|command| format!("\"{}\"", command))
+            .collect::<Vec<String>>()
+            .join(", "),
+        Shell::Fish | Shell::Cmd => pass_through.join(" "),
+    };
+    template
+        .replace("{name}", name)
+        .replace("{exe}", &shell.quote(exe))
+        .replace("{commands}", &commands)
+}
+
+const POSIX: &str = r#"# sgr: jump to a repo with `{name}`, from `sugar init`
+{name}() {
+    case "$1" in
+        {commands})
+            command {exe} "$@"
+            ;;
+        *)
+            # in a pipe there is nowhere to cd, so the paths go through as is
+            if [ ! -t 1 ]; then
+                command {exe} "$@"
+                return
+            fi
+            local sgr_picked
+            # SGR_CD sends what the print action prints to stderr, so stdout
+            # only has paths to cd to
+            sgr_picked="$(SGR_CD=1 command {exe} "$@")" || return
+            # actions other than cd, like opening an editor, print nothing
+            [ -n "$sgr_picked" ] || return 0
+            # --multi picked several repos, so there is no one place to cd to
+            if [ "$(printf '%s\n' "$sgr_picked" | wc -l)" -gt 1 ]; then
+                printf '%s\n' "$sgr_picked"
+            else
+                cd -- "$sgr_picked" || return
+            fi
+            ;;
+    esac
+}
+"#;
+
+const ZSH_WIDGETS: &str = r#"
+# {jump_key} picks a repo and cds to it, {insert_key} inserts its path at the
+# cursor
+_{name}_jump() {
+    local sgr_picked
+    # what the print action prints goes below the prompt
+    zle -I
+    sgr_picked="$(SGR_CD=1 command {exe} run --action cd)" && [ -n "$sgr_picked" ] && cd -- "$sgr_picked"
+    # hooks like vcs_info update what the prompt shows for the new directory
+    local sgr_precmd
+    for sgr_precmd in $precmd_functions; do
+        "$sgr_precmd"
+    done
+    zle reset-prompt
+}
+_{name}_insert() {
+    local sgr_picked
+    sgr_picked="$(SGR_CD=1 command {exe} run --action cd)" && [ -n "$sgr_picked" ] && LBUFFER+="${(q)sgr_picked}"
+    zle reset-prompt
+}
+zle -N _{name}_jump
+zle -N _{name}_insert
+bindkey '{jump}' _{name}_jump
+bindkey '{insert}' _{name}_insert
+"#;
+
+const BASH_WIDGETS: &str = r#"
+# {jump_key} picks a repo and cds to it, {insert_key} inserts its path at the
+# cursor
+_{name}_jump() {
+    local sgr_picked
+    sgr_picked="$(SGR_CD=1 command {exe} run --action cd)" && [ -n "$sgr_picked" ] && cd -- "$sgr_picked"
+}
+_{name}_insert() {
+    local sgr_picked
+    sgr_picked="$(SGR_CD=1 command {exe} run --action cd)" && [ -n "$sgr_picked" ] || return
+    sgr_picked="$(printf '%q' "$sgr_picked")"
+    READLINE_LINE="${READLINE_LINE:0:READLINE_POINT}$sgr_picked${READLINE_LINE:READLINE_POINT}"
+    READLINE_POINT=$((READLINE_POINT + ${#sgr_picked}))
+}
+# only interactive shells have a line editor to bind keys in
+if [[ $- == *i* ]]; then
+    bind -x '"{jump}": _{name}_jump'
+    bind -x '"{insert}": _{name}_insert'
+fi
+"#;
+
+const FISH_WIDGETS: &str = r#"
+# {jump_key} picks a repo and cds to it, {insert_key} inserts its path at the
+# cursor
+function _{name}_jump
+    set -l sgr_picked (SGR_CD=1 command {exe} run --action cd); and set -q sgr_picked[1]; and cd $sgr_picked[1]
+    commandline -f repaint
+end
+function _{name}_insert
+    set -l sgr_picked (SGR_CD=1 command {exe} run --action cd); and set -q sgr_picked[1]; and commandline -i -- (string escape -- $sgr_picked)
+    commandline -f repaint
+end
+bind {jump} _{name}_jump
+bind {insert} _{name}_insert
+"#;
+
+const FISH: &str = r#"# sgr: jump to a repo with `{name}`, from `sugar init`
+function {name}
+    switch "$argv[1]"
+        case {commands}
+            command {exe} $argv
+        case '*'
+            # in a pipe there is nowhere to cd, so the paths go through as is
+            if not isatty stdout
+                command {exe} $argv
+                return
+            end
+            # SGR_CD sends what the print action prints to stderr, so stdout
+            # only has paths to cd to
+            set -l sgr_picked (SGR_CD=1 command {exe} $argv)
+            or return
+            # actions other than cd, like opening an editor, print nothing
+            set -q sgr_picked[1]; or return 0
+            # --multi picked several repos, so there is no one place to cd to
+            if test (count $sgr_picked) -gt 1
+                printf '%s\n' $sgr_picked
+            else
+                cd $sgr_picked[1]
+            end
+    end
+end
+"#;
+
+const NUSHELL: &str = r#"# sgr: jump to a repo with `{name}`, from `sugar init`
+def --env --wrapped {name} [...args: string] {
+    if ($args | is-not-empty) and ($args | first) in [{commands}] {
+        ^{exe} ...$args
+        return
+    }
+    # SGR_CD sends what the print action prints to stderr, so stdout only
+    # has paths to cd to
+    let sgr = (with-env {SGR_CD: "1"} { ^{exe} ...$args | complete })
+    print --stderr --no-newline $sgr.stderr
+    if $sgr.exit_code != 0 {
+        return
+    }
+    let picked = ($sgr.stdout | lines)
+    # actions other than cd, like opening an editor, print nothing
+    if ($picked | is-empty) {
+        return
+    }
+    # --multi picked several repos, so there is no one place to cd to
+    if ($picked | length) > 1 {
+        $picked | each {|path| print $path } | ignore
+    } else {
+        cd ($picked | first)
+    }
+}
+"#;
+
+const PWSH: &str = r#"# sgr: jump to a repo with `{name}`, from `sugar init`
+function global:{name} {
+    if ($args.Count -gt 0 -and @({commands}) -contains $args[0])
+    {
+        & {exe} @args
+        return
+    }
+    # SGR_CD sends what the print action prints to stderr, so the output
+    # only has paths to cd to
+    $env:SGR_CD = 1
+    try
+    {
+        $res = & {exe} @args
+    }
+    finally
+    {
+        Remove-Item Env:SGR_CD
+    }
+    # cancelled, no repos, bad config, and so on
+    if ($LASTEXITCODE -ne 0)
+    {
+        return
+    }
+    $picked = @($res | Where-Object { $_ -ne "" })
+    # actions other than cd, like opening an editor, print nothing
+    if ($picked.Count -eq 0)
+    {
+        return
+    }
+
+    # --multi picked several repos; there is no one directory to cd to, so
+    # they are listed and kept in $SgrPicked for the next command instead
+    if ($picked.Count -gt 1)
+    {
+        $global:SgrPicked = $picked
+        "Picked $($picked.Count) repos, kept in `$SgrPicked:"
+        $picked
+        return
+    }
+    Set-Location -LiteralPath $picked[0]
+}
+"#;
+
+const CMD: &str = r#"@echo off
+rem sgr: jump to a repo with `{name}`, from `sugar init cmd`; save it as
+rem {name}.bat somewhere in PATH
+
+rem anything but these commands, like run, query or keywords to search for,
+rem prints a path to cd to
+for %%c in ({commands}) do (
+    if "%~1"=="%%c" goto pass_through
+)
+goto select_repos
+
+rem outside of the loop so %errorlevel% is read after sgr ran
+:pass_through
+    {exe} %*
+    set "sgr_exit=%errorlevel%"
+    goto end
+
+:select_repos
+    rem only the chosen paths are printed to stdout; errors and warnings go to
+    rem stderr, straight to the console. Kept in a file in case --multi picked
+    rem several repos. SGR_CD sends what the print action prints to stderr
+    set "SGR_CD=1"
+    {exe} %* > "%TEMP%\sgr_selected.txt"
+    set "sgr_exit=%errorlevel%"
+    set "SGR_CD="
+
+    rem cancelled, no repos, bad config, and so on
+    if not "%sgr_exit%"=="0" goto end
+
+    set "selected_count=0"
+    for /f "usebackq tokens=*" %%i in ("%TEMP%\sgr_selected.txt") do (
+        set "selected_path=%%i"
+        set /a selected_count+=1
+    )
+
+    rem actions other than cd, like opening an editor, print nothing
+    if %selected_count%==0 goto end
+
+    rem there is no one directory to cd to, so list them instead
+    if %selected_count% gtr 1 (
+        echo Picked %selected_count% repos:
+        type "%TEMP%\sgr_selected.txt"
+        goto end
+    )
+
+    cd /d "%selected_path%"
+
+rem remove env vars, passing on sgr's exit code
+:end
+    if exist "%TEMP%\sgr_selected.txt" del "%TEMP%\sgr_selected.txt"
+    set "selected_path="
+    set "selected_count="
+    set "sgr_exit=" & exit /b %sgr_exit%
+"#;
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "init"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "init".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!(
+        "{}prints a shell function that cds to the chosen repo,",
+        title
+    );
+    println!(
+        "{}for bash, zsh, fish, nushell, pwsh or cmd\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!(
+            "{}",
+            "eval \"$(sugar init bash)\"       # ~/.bashrc"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "eval \"$(sugar init zsh)\"        # ~/.zshrc"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "sugar init fish | source        # config.fish"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "sugar init nushell | save -f ~/.sgr.nu; source ~/.sgr.nu"
+                .to_string()
+                .fill_left(17)
+        );
+        println!(
+            "{}",
+            "Invoke-Expression (& sugar init pwsh | Out-String)"
+                .to_string()
+                .fill_left(17)
+        );
+        println!("{}", "sugar init cmd > sgr.bat".to_string().fill_left(17));
+        println!(
+            "{}",
+            "sugar init bash --function j".to_string().fill_left(17)
+        );
+        println!("{}", "sugar init zsh --bind".to_string().fill_left(17));
+        println!(
+            "{}",
+            "sugar init fish --jump-key alt-j --insert-key ctrl-alt-j"
+                .to_string()
+                .fill_left(17)
+        );
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. The function is called \"sgr\" unless \"--function\" says otherwise");
+        println!("  2. Commands that do not print a path, like \"list\", run as they are");
+        println!("  3. In bash, zsh and fish, paths are printed instead of cd'd to when");
+        println!("     the output is piped, like \"sgr --multi | xargs code\"");
+        println!("  4. With \"--bind\", bash, zsh and fish also get keys that open the");
+        println!("     picker: Ctrl-G cds to the chosen repo and Alt-G inserts its path at");
+        println!("     the cursor. \"--jump-key\" and \"--insert-key\" bind other keys, as");
+        println!("     \"ctrl-\" and/or \"alt-\" then a letter, or \"alt-\" then a digit");
+    }
+}
@@ -0,0 +1,112 @@
+// own
+use crate::utils::{format_log, LogLevel};
+
+/// a key with modifiers to bind a widget to, like `ctrl-g`; fzf names keys
+/// the same way
+#[derive(Clone, PartialEq)]
+pub struct Key {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub key: char,
+}
+
+impl Key {
+    /// `ctrl-` and/or `alt-` in front of a letter or digit, like `alt-g` or
+    /// `ctrl-alt-g`; ctrl only goes with letters. `what` is where it came
+    /// from for errors, like `'--jump-key'`
+    pub fn parse(what: &str, spec: &str) -> Result<Key, String> {
+        let invalid = || {
+            format_log(
+                LogLevel::Error,
+                format!(
+                    "{} expects a key like 'ctrl-g' or 'alt-g'; got '{}'",
+                    what, spec
+                ),
+            )
+        };
+        let lowercase = spec.to_lowercase();
+        let mut parts: Vec<&str> = lowercase.split('-').collect();
+        let mut chars = parts.pop().unwrap_or_default().chars();
+        let (Some(key), None) = (chars.next(), chars.next()) else {
+            return Err(invalid());
+        };
+
+        let mut parsed = Key {
+            ctrl: false,
+            alt: false,
+            key,
+        };
+        for modifier in parts {
+            match modifier {
+                "ctrl" if !parsed.ctrl => parsed.ctrl = true,
+                "alt" if !parsed.alt => parsed.alt = true,
+                _ => return Err(invalid()),
+            }
+        }
+        let valid = match parsed.ctrl {
+            true => key.is_ascii_lowercase(),
+            false => parsed.alt && key.is_ascii_alphanumeric(),
+        };
+        match valid {
+            true => Ok(parsed),
+            false => Err(invalid()),
+        }
+    }
+
+    /// the key as `sgr init` and fzf take it
+    pub fn name(&self) -> String {
+        let ctrl = if self.ctrl { "ctrl-" } else { "" };
+        let alt = if self.alt { "alt-" } else { "" };
+        format!("{}{}{}", ctrl, alt, self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(spec: &str) -> Result<String, String> {
+        Key::parse("'--jump-key'", spec).map(|key| key.name())
+    }
+
+    #[test]
+    fn modifiers() {
+        let key = Key::parse("'--jump-key'", "ctrl-alt-g").unwrap();
+        assert!(key.ctrl && key.alt && key.key == 'g');
+        assert_eq!(name("ctrl-g").unwrap(), "ctrl-g");
+        assert_eq!(name("alt-g").unwrap(), "alt-g");
+        assert_eq!(name("alt-7").unwrap(), "alt-7");
+    }
+
+    #[test]
+    fn names_are_normalized() {
+        assert_eq!(name("CTRL-G").unwrap(), "ctrl-g");
+        assert_eq!(name("alt-ctrl-g").unwrap(), "ctrl-alt-g");
+        assert!(Key::parse("", "Ctrl-g") == Key::parse("", "ctrl-G"));
+    }
+
+    #[test]
+    fn invalid_keys() {
+        for spec in [
+            "",
+            "g",
+            "7",
+            "ctrl-",
+            "ctrl-gg",
+            "ctrl-7",
+            "ctrl-ctrl-g",
+            "shift-g",
+            "alt-/",
+            "ctrl+g",
+        ] {
+            assert!(name(spec).is_err(), "'{}' should be invalid", spec);
+        }
+    }
+
+    #[test]
+    fn error_says_where_the_key_came_from() {
+        let error = name("g").unwrap_err();
+        assert!(error.contains("'--jump-key'"));
+        assert!(error.contains("'g'"));
+    }
+}
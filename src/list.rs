@@ -1,25 +1,53 @@
 // std lib
 use std::fs::OpenOptions;
 use std::io::{BufRead, BufReader, Seek};
+use std::path::PathBuf;
 
 // own
+use crate::config;
+use crate::flags::Flags;
+use crate::format::{self, Format};
 use crate::utils::{format_log, LogLevel, StrExt, StringExt};
 
 pub fn execute(arg: Option<&str>, header: Option<String>) -> Result<(), String> {
+    let (file_path, line_count, entries) = entries(arg)?;
+
+    let header = header.unwrap_or(format!("({}) {:?}", line_count, file_path));
+    println!("----------------------------------------------------");
+    println!("| {}", header);
+    println!("----------------------------------------------------");
+    let line_pad = 2;
+    for (line_num, line) in entries {
+        println!("| {:0>line_pad$} | {}", line_num, line);
+    }
+    println!("----------------------------------------------------");
+    Ok(())
+}
+
+/// The entries `execute` would print, one record each as `--format` says,
+/// for scripts.
+pub fn records(arg: Option<&str>, flags: &Flags) -> Result<String, String> {
+    let (_, _, entries) = entries(arg)?;
+    let format = flags.format.unwrap_or(Format::Plain);
+    let records: Vec<String> = entries
+        .iter()
+        // blank lines are not roots
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_num, line)| format::entry(*line_num, line, format))
+        .collect();
+    Ok(format::join(&records, flags))
+}
+
+/// a line of dirs.txt after its line number
+type Entry = (usize, String);
+
+/// dirs.txt, how many lines it has, and its lines `arg` picks out
+fn entries(arg: Option<&str>) -> Result<(PathBuf, usize, Vec<Entry>), String> {
     // default arg
     let arg = arg.unwrap_or("all");
 
     // open files and initialze readers, writers
-    let file_path = std::env::current_exe()
-        .map_err(|e| {
-            format_log(
-                LogLevel::Error,
-                format!("Failed to get sgr.exe path: {}", e),
-            )
-        })?
-        .parent()
-        .ok_or("Failed to get parent directory of sgr.exe")?
-        .join("dirs.txt");
+    let file_path = config::dirs_txt_path()?;
     let mut file = OpenOptions::new()
         .read(true)
         .open(&file_path)
@@ -38,25 +66,15 @@ pub fn execute(arg: Option<&str>, header: Option<String>) -> Result<(), String>
     _ = file.seek(std::io::SeekFrom::Start(0));
     let lines = BufReader::new(&file).lines().filter_map(|line| line.ok());
 
-    let header = header.unwrap_or(format!("({}) {:?}", line_count, file_path));
-    println!("----------------------------------------------------");
-    println!("| {}", header);
-    println!("----------------------------------------------------");
-    let line_pad = 2;
-
-    if arg == "all" || arg == "" {
-        lines
-            .enumerate()
-            .for_each(|(i, line)| println!("| {:0>line_pad$} | {}", i + 1, line));
+    let lines = lines.enumerate().map(|(i, line)| (i + 1, line));
+    let entries = if arg == "all" || arg == "" {
+        lines.collect()
     } else if arg.chars().all(|char| char.is_digit(10)) {
         let line_num: usize = arg
             .parse()
             .map_err(|e| format_log(LogLevel::Error, format!("Failed to parse arg: {}", e)))?;
 
-        lines
-            .enumerate()
-            .filter(|(i, _)| i + 1 == line_num)
-            .for_each(|(i, line)| println!("| {:0>line_pad$} | {}", i + 1, line));
+        lines.filter(|(i, _)| *i == line_num).collect()
     } else if arg.is_digit_range() {
         let parts: Vec<&str> = arg.split('-').collect();
         let start = parts[0].parse::<usize>().unwrap();
@@ -87,25 +105,18 @@ pub fn execute(arg: Option<&str>, header: Option<String>) -> Result<(), String>
             ));
         }
 
-        lines
-            .enumerate()
-            .filter(|(i, _)| i + 1 >= start && i + 1 <= end)
-            .for_each(|(i, line)| println!("| {:0>line_pad$} | {}", i + 1, line));
+        lines.filter(|(i, _)| *i >= start && *i <= end).collect()
     } else {
         let pattern = &arg.to_lowercase();
 
         lines
-            .enumerate()
             .filter(|(_, line)| {
                 line.to_lowercase().contains(pattern) || line.to_lowercase().contains(pattern)
             })
-            .for_each(|(i, line)| {
-                println!("| {:0>line_pad$} | {}", i + 1, line);
-            })
-    }
+            .collect()
+    };
 
-    println!("----------------------------------------------------");
-    Ok(())
+    Ok((file_path, line_count, entries))
 }
 
 pub fn help(verbose: bool) {
@@ -145,11 +156,14 @@ pub fn help(verbose: bool) {
         println!("{}", "sgr list 1".to_string().fill_left(17));
         println!("{}", "sgr list 1-3".to_string().fill_left(17));
         println!("{}", "sgr list path/pattern".to_string().fill_left(17));
+        println!("{}", "sgr list --format json".to_string().fill_left(17));
 
         println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
         println!("  1. Line number bounds are checked");
         println!("  2. Line range is inclusive");
         println!("  3. Pattern is case insensitive but exact match is required");
         println!("     if none was found, nothing will be printed");
+        println!("  4. \"--format json|tsv|plain\" or \"--print0\" print the line, directory");
+        println!("     and options of each as records for scripts instead of a table");
     }
 }
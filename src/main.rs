@@ -1,65 +1,103 @@
 // commands
+mod action;
 mod add;
+mod cache;
+mod completions;
+mod daemon;
 mod help;
+mod history;
+mod init;
 mod list;
+mod preview;
+mod query;
 mod remove;
 mod run;
 mod version;
 
 // helper functions
+mod config;
+mod detect;
+mod discover;
+mod flags;
+mod format;
+mod fuzzy;
+mod key;
+mod picker;
+mod rows;
+mod selector;
 mod utils;
-use utils::{logln, LogLevel};
+use flags::Flags;
+use utils::{elogln, Exit, Failure, LogLevel};
 
 fn main() {
     let raw_args: Vec<String> = std::env::args().collect();
     let res = parse_args(&raw_args);
-    let (command, arg) = match res {
+    let (command, arg, flags) = match res {
         Ok(res) => res,
         Err(e) => {
-            println!("{}", e);
-            return;
+            eprintln!("{}", e);
+            std::process::exit(Exit::Config as i32);
         }
     };
 
-    match command.execute(arg) {
+    match command.execute(arg.as_deref(), &flags) {
+        // NUL ended paths have no newline after the last one
+        // like actions other than cd, which leave nothing to cd to
+        Ok(res) if res.is_empty() => {}
+        Ok(res) if flags.print0 => print!("{}", res),
         Ok(res) => println!("{}", res),
-        Err(e) => println!("{}", e),
+        Err(failure) => {
+            if !failure.message.is_empty() {
+                eprintln!("{}", failure.message);
+            }
+            std::process::exit(failure.exit as i32);
+        }
     }
 }
 
-fn parse_args(raw_args: &[String]) -> Result<(CMD, Option<&str>), String> {
-    if raw_args.len() < 2 {
-        return Ok((CMD::Run, None));
+fn parse_args(raw_args: &[String]) -> Result<(CMD, Option<String>, Flags), String> {
+    let (flags, args) = Flags::parse(raw_args.get(1..).unwrap_or_default())?;
+    if args.is_empty() {
+        return Ok((CMD::Run, None, flags));
     }
 
-    // validate command
-    let cmd = match to_command(raw_args[1].as_str()) {
-        Ok(cmd) => cmd,
-        Err(e) => return Err(e),
+    // anything that is not a command is a query to open the picker with
+    let (cmd, args) = match to_command(args[0]) {
+        Ok(cmd) => (cmd, &args[1..]),
+        Err(_) => (CMD::Run, &args[..]),
     };
 
+    // the only commands taking more than one arg
+    if let CMD::Run | CMD::Query = cmd {
+        let keywords = (!args.is_empty()).then(|| args.join(" "));
+        return Ok((cmd, keywords, flags));
+    }
+
     // warn user of unused args
-    let args = &raw_args[2..];
     if args.len() > 1 {
-        logln(
+        elogln(
             LogLevel::Warn,
             format!("args '{:?}' will be unused", args[1..].to_vec()),
         );
     }
 
-    let arg = match args.is_empty() {
-        true => None,
-        false => Some(args[0].as_str()),
-    };
+    let arg = args.first().map(|arg| arg.to_string());
 
-    return Ok((cmd, arg));
+    return Ok((cmd, arg, flags));
 }
 
 // add commands here
 pub enum CMD {
     Run,
+    Query,
     Add,
     Remove,
+    Cache,
+    Daemon,
+    History,
+    Preview,
+    Init,
+    Completions,
     List,
     Help,
     Version,
@@ -69,29 +107,58 @@ pub enum CMD {
 impl CMD {
     fn help(&self, verbose: bool) {
         match self {
-            CMD::Run => {} // impossible
+            CMD::Run => run::help(verbose),
+            CMD::Query => query::help(verbose),
             CMD::Add => add::help(verbose),
             CMD::Remove => remove::help(verbose),
+            CMD::Cache => cache::help(verbose),
+            CMD::Daemon => daemon::help(verbose),
+            CMD::History => history::help(verbose),
+            CMD::Preview => preview::help(verbose),
+            CMD::Init => init::help(verbose),
+            CMD::Completions => completions::help(verbose),
             CMD::List => list::help(verbose),
             CMD::Help => help::help(verbose),
             CMD::Version => version::help(verbose),
         }
     }
 
-    fn execute(&self, arg: Option<&str>) -> Result<String, String> {
-        let mut res: Result<(), String> = Ok(());
+    fn execute(&self, arg: Option<&str>, flags: &Flags) -> Result<String, Failure> {
+        let mut res: Result<(), Failure> = Ok(());
         match self {
-            // only one with success message: the path to cd to
+            // only ones with success messages: the path to cd to
             CMD::Run => {
-                return run::execute();
+                return run::execute(arg, flags);
+            }
+            CMD::Query => {
+                return query::execute(arg, flags);
+            }
+            // the shell function to eval
+            CMD::Init => {
+                return init::execute(arg, flags).map_err(Failure::from);
+            }
+            // records for scripts
+            CMD::List if flags.format.is_some() || flags.print0 => {
+                return list::records(arg, flags).map_err(Failure::from);
+            }
+            CMD::History if flags.format.is_some() || flags.print0 => {
+                return history::records(arg, flags).map_err(Failure::from);
+            }
+            // the completion script, or what an arg can be
+            CMD::Completions => {
+                return completions::execute(arg, flags).map_err(Failure::from);
             }
 
             // no success messages
             CMD::Version => version::execute(),
-            CMD::Help => res = help::execute(arg),
-            CMD::Add => res = add::execute(arg),
-            CMD::Remove => res = remove::execute(arg),
-            CMD::List => res = list::execute(arg, None),
+            CMD::Help => res = help::execute(arg).map_err(Failure::from),
+            CMD::Add => res = add::execute(arg, flags),
+            CMD::Remove => res = remove::execute(arg).map_err(Failure::from),
+            CMD::Cache => res = cache::execute(arg, flags),
+            CMD::Daemon => res = daemon::execute(arg, flags),
+            CMD::History => res = history::execute(arg, flags).map_err(Failure::from),
+            CMD::Preview => res = preview::execute(arg, flags),
+            CMD::List => res = list::execute(arg, None).map_err(Failure::from),
         };
 
         match res {
@@ -101,10 +168,35 @@ impl CMD {
     }
 }
 
+/// every name `to_command` takes, for completions
+pub const COMMANDS: [&str; 13] = [
+    "run",
+    "query",
+    "add",
+    "remove",
+    "cache",
+    "daemon",
+    "history",
+    "preview",
+    "init",
+    "completions",
+    "list",
+    "help",
+    "version",
+];
+
 fn to_command(command: &str) -> Result<CMD, String> {
     match command {
+        "run" => Ok(CMD::Run),
+        "query" => Ok(CMD::Query),
         "add" => Ok(CMD::Add),
         "remove" => Ok(CMD::Remove),
+        "cache" => Ok(CMD::Cache),
+        "daemon" => Ok(CMD::Daemon),
+        "history" => Ok(CMD::History),
+        "preview" => Ok(CMD::Preview),
+        "init" => Ok(CMD::Init),
+        "completions" => Ok(CMD::Completions),
         "list" => Ok(CMD::List),
         "help" => Ok(CMD::Help),
         "version" => Ok(CMD::Version),
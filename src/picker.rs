@@ -0,0 +1,489 @@
+// standard library
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// third party
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+
+// own
+use crate::config::Settings;
+use crate::discover::Report;
+use crate::fuzzy::{self, Match};
+use crate::key::Key;
+use crate::selector::Picked;
+use crate::utils::{format_log, LogLevel};
+
+/// the built in picker's own terminal, restored when dropped even on errors.
+/// It is drawn on the tty itself since stdout is for the chosen path
+struct Terminal {
+    tty: File,
+}
+
+impl Terminal {
+    fn open() -> std::io::Result<Terminal> {
+        #[cfg(windows)]
+        let tty = OpenOptions::new().write(true).open("CONOUT$")?;
+        #[cfg(not(windows))]
+        let tty = OpenOptions::new().write(true).open("/dev/tty")?;
+
+        terminal::enable_raw_mode()?;
+        let mut terminal = Terminal { tty };
+        execute!(terminal.tty, EnterAlternateScreen)?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        _ = execute!(self.tty, LeaveAlternateScreen, cursor::Show);
+        _ = terminal::disable_raw_mode();
+    }
+}
+
+/// what the picker shows
+struct State {
+    lines: Vec<String>,
+    query: String,
+    /// indices into `lines` matching `query`, best first
+    matches: Vec<(usize, Match)>,
+    /// index into `matches` of the highlighted line
+    cursor: usize,
+    /// index into `matches` of the first line on screen
+    offset: usize,
+    /// more lines may still come
+    loading: bool,
+    /// lines can be marked to pick several
+    multi: bool,
+    /// indices into `lines` of marked lines, in the order they are listed
+    marked: BTreeSet<usize>,
+    /// keys that pick like Enter does, but are reported back
+    keys: Vec<Key>,
+}
+
+enum Action {
+    Redraw,
+    Select,
+    /// picked with one of `keys`
+    SelectWith(String),
+    Cancel,
+    None,
+}
+
+impl State {
+    fn rematch(&mut self) {
+        let query = &self.query;
+        self.matches = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| fuzzy::fuzzy_match(query, line).map(|m| (i, m)))
+            .collect();
+        // lines keep coming in order, so only reorder them once there is
+        // something to rank them by
+        if !query.trim().is_empty() {
+            let lines = &self.lines;
+            self.matches.sort_by(|(a, a_match), (b, b_match)| {
+                b_match
+                    .score
+                    .cmp(&a_match.score)
+                    .then_with(|| lines[*a].len().cmp(&lines[*b].len()))
+                    .then_with(|| a.cmp(b))
+            });
+        }
+        self.cursor = self.cursor.min(self.matches.len().saturating_sub(1));
+    }
+
+    /// Puts `lines` in `order`, keeping the same lines highlighted and
+    /// marked. Lines missing from it stay after the rest as they were.
+    fn reorder(&mut self, order: &[String]) {
+        let position: HashMap<&str, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (line.as_str(), i))
+            .collect();
+        let mut indices: Vec<usize> = (0..self.lines.len()).collect();
+        indices.sort_by_key(|&i| {
+            position
+                .get(self.lines[i].as_str())
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+
+        let mut moved_to = vec![0; indices.len()];
+        for (to, &from) in indices.iter().enumerate() {
+            moved_to[from] = to;
+        }
+        let highlighted = self
+            .matches
+            .get(self.cursor)
+            .map(|(line, _)| moved_to[*line]);
+        let mut lines = std::mem::take(&mut self.lines);
+        self.lines = indices
+            .iter()
+            .map(|&from| std::mem::take(&mut lines[from]))
+            .collect();
+        self.marked = self.marked.iter().map(|&from| moved_to[from]).collect();
+        self.rematch();
+        if let Some(highlighted) = highlighted {
+            self.cursor = self
+                .matches
+                .iter()
+                .position(|(line, _)| *line == highlighted)
+                .unwrap_or(0);
+        }
+    }
+
+    fn on_key(&mut self, key: KeyEvent, rows: usize) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        // before the picker's own keys so these win
+        if let (KeyCode::Char(c), false) = (key.code, self.matches.is_empty()) {
+            let pressed = Key {
+                ctrl,
+                alt,
+                key: c.to_ascii_lowercase(),
+            };
+            if self.keys.contains(&pressed) {
+                return Action::SelectWith(pressed.name());
+            }
+        }
+        match key.code {
+            KeyCode::Esc => Action::Cancel,
+            KeyCode::Char('c' | 'g' | 'q') if ctrl => Action::Cancel,
+            KeyCode::Enter if !self.matches.is_empty() => Action::Select,
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p' | 'k') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n' | 'j') if ctrl => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(rows as isize)),
+            KeyCode::PageDown => self.move_cursor(rows as isize),
+            KeyCode::Tab if self.multi => self.toggle_mark(1),
+            KeyCode::BackTab if self.multi => self.toggle_mark(-1),
+            KeyCode::Backspace => self.edit(|query| _ = query.pop()),
+            KeyCode::Char('u') if ctrl => self.edit(String::clear),
+            KeyCode::Char('w') if ctrl => self.edit(|query| {
+                let kept = query.trim_end().rfind(' ').map_or(0, |i| i + 1);
+                query.truncate(kept);
+            }),
+            KeyCode::Char(c) if !ctrl && !alt => self.edit(|query| query.push(c)),
+            _ => Action::None,
+        }
+    }
+
+    fn move_cursor(&mut self, by: isize) -> Action {
+        let last = self.matches.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(by).min(last);
+        Action::Redraw
+    }
+
+    /// marks or unmarks the highlighted line, then moves on like fzf does
+    fn toggle_mark(&mut self, by: isize) -> Action {
+        if let Some((line, _)) = self.matches.get(self.cursor) {
+            if !self.marked.remove(line) {
+                self.marked.insert(*line);
+            }
+        }
+        self.move_cursor(by)
+    }
+
+    fn edit<F>(&mut self, edit: F) -> Action
+    where
+        F: FnOnce(&mut String),
+    {
+        edit(&mut self.query);
+        self.cursor = 0;
+        self.rematch();
+        Action::Redraw
+    }
+
+    /// the prompt, a count of matches, then as many matches as fit
+    fn draw(&mut self, tty: &mut File) -> std::io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, rows) = (width as usize, (height as usize).saturating_sub(2));
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + rows {
+            self.offset = self.cursor + 1 - rows;
+        }
+
+        queue!(
+            tty,
+            cursor::Hide,
+            cursor::MoveTo(0, 0),
+            SetForegroundColor(Color::Blue),
+            Print("> "),
+            ResetColor,
+            Print(&self.query),
+            Clear(ClearType::UntilNewLine),
+            cursor::MoveTo(0, 1),
+            SetForegroundColor(Color::DarkGrey),
+            Print(format!(
+                "  {}/{}{}{}",
+                self.matches.len(),
+                self.lines.len(),
+                match self.marked.len() {
+                    0 => String::new(),
+                    marked => format!(" ({} marked)", marked),
+                },
+                if self.loading { " ..." } else { "" }
+            )),
+            ResetColor,
+            Clear(ClearType::UntilNewLine),
+        )?;
+
+        let visible = self.matches.iter().enumerate().skip(self.offset).take(rows);
+        for (row, (i, (line, m))) in visible.enumerate() {
+            queue!(tty, cursor::MoveTo(0, row as u16 + 2))?;
+            let selected = i == self.cursor;
+            match selected {
+                true => queue!(tty, SetForegroundColor(Color::Red), Print(">"))?,
+                false => queue!(tty, Print(" "))?,
+            }
+            match self.marked.contains(line) {
+                true => queue!(tty, SetForegroundColor(Color::Magenta), Print("+"))?,
+                false => queue!(tty, Print(" "))?,
+            }
+            queue!(tty, ResetColor)?;
+            if selected {
+                queue!(tty, SetAttribute(Attribute::Bold))?;
+            }
+            draw_line(
+                tty,
+                &self.lines[*line],
+                &m.positions,
+                width.saturating_sub(2),
+            )?;
+            queue!(
+                tty,
+                SetAttribute(Attribute::Reset),
+                Clear(ClearType::UntilNewLine)
+            )?;
+        }
+        let drawn = self.matches.len().saturating_sub(self.offset).min(rows);
+        queue!(
+            tty,
+            cursor::MoveTo(0, drawn as u16 + 2),
+            Clear(ClearType::FromCursorDown),
+            cursor::MoveTo(2 + self.query.chars().count() as u16, 0),
+            cursor::Show
+        )?;
+        tty.flush()
+    }
+}
+
+/// Prints up to `width` chars of `line` with the chars at `positions`
+/// highlighted. Whatever comes after a tab is a label and drawn dimmed.
+fn draw_line(tty: &mut File, line: &str, positions: &[usize], width: usize) -> std::io::Result<()> {
+    let mut positions = positions.iter().peekable();
+    let mut drawn = 0;
+    let mut label = false;
+    for (i, c) in line.chars().enumerate() {
+        if drawn >= width {
+            break;
+        }
+        let matched = positions.next_if_eq(&&i).is_some();
+        if c == '\t' {
+            label = true;
+            queue!(tty, SetForegroundColor(Color::DarkGrey), Print("  "))?;
+            drawn += 2;
+            continue;
+        }
+        match matched {
+            true => queue!(
+                tty,
+                SetForegroundColor(Color::Green),
+                Print(c),
+                SetForegroundColor(match label {
+                    true => Color::DarkGrey,
+                    false => Color::Reset,
+                })
+            )?,
+            false => queue!(tty, Print(c))?,
+        }
+        drawn += 1;
+    }
+    queue!(tty, ResetColor)
+}
+
+/// Lets the user fuzzy find one of `lines`, which are shown as they arrive
+/// until the sender is dropped or the `timeout` setting runs out, starting out
+/// with `query` typed in. They are put in `order` then, as far as it has them.
+/// Returns the chosen lines, or `None` if the picker was cancelled with Esc or
+/// Ctrl-C.
+///
+/// Typing narrows the list down, best matches first. Up/Down or Ctrl-P/N move
+/// the cursor and Enter picks the line under it. With `multi`, Tab and
+/// Shift-Tab mark lines and Enter picks the marked ones instead if there are
+/// any. The keys of `action-keys` pick the same way as Enter, along with the
+/// key that did.
+pub fn pick(
+    lines: Receiver<String>,
+    order: &Mutex<Vec<String>>,
+    query: &str,
+    multi: bool,
+    settings: &Settings,
+    report: &Report,
+) -> Result<Option<Picked>, String> {
+    let deadline = settings.timeout.map(|timeout| Instant::now() + timeout);
+    // dropped once out of time, which tells the search to stop
+    let mut lines = Some(lines);
+    let to_error = |e: std::io::Error| format_log(LogLevel::Error, format!("picker: {}", e));
+    let mut terminal = Terminal::open().map_err(to_error)?;
+    let mut state = State {
+        lines: Vec::new(),
+        query: query.to_string(),
+        matches: Vec::new(),
+        cursor: 0,
+        offset: 0,
+        loading: true,
+        multi,
+        marked: BTreeSet::new(),
+        keys: settings
+            .action_keys
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect(),
+    };
+    let mut dirty = true;
+
+    loop {
+        if state.loading && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            report.time_out();
+            lines = None;
+            state.loading = false;
+            dirty = true;
+        }
+        while let Some(receiver) = &lines {
+            match receiver.try_recv() {
+                Ok(line) => {
+                    state.lines.push(line);
+                    dirty = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if state.loading {
+                        if let Ok(order) = order.lock() {
+                            state.reorder(&order);
+                        }
+                        dirty = true;
+                    }
+                    state.loading = false;
+                    break;
+                }
+            }
+        }
+        if dirty {
+            state.rematch();
+            state.draw(&mut terminal.tty).map_err(to_error)?;
+            dirty = false;
+        }
+
+        if !event::poll(Duration::from_millis(30)).map_err(to_error)? {
+            continue;
+        }
+        let action = match event::read().map_err(to_error)? {
+            // windows also reports key releases
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                let rows = terminal::size().map_or(0, |(_, height)| height as usize);
+                state.on_key(key, rows.saturating_sub(2))
+            }
+            Event::Resize(..) => Action::Redraw,
+            _ => Action::None,
+        };
+        let key = match action {
+            Action::Select => None,
+            Action::SelectWith(key) => Some(key),
+            Action::Cancel => return Ok(None),
+            Action::Redraw => {
+                state.draw(&mut terminal.tty).map_err(to_error)?;
+                continue;
+            }
+            Action::None => continue,
+        };
+        let picked = match state.marked.is_empty() {
+            true => vec![state.matches[state.cursor].0],
+            false => state.marked.into_iter().collect(),
+        };
+        return Ok(Some(Picked {
+            lines: picked
+                .into_iter()
+                .map(|line| std::mem::take(&mut state.lines[line]))
+                .collect(),
+            key,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(lines: &[&str], query: &str) -> State {
+        let mut state = State {
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            query: query.to_string(),
+            matches: Vec::new(),
+            cursor: 0,
+            offset: 0,
+            loading: false,
+            multi: true,
+            marked: BTreeSet::new(),
+            keys: Vec::new(),
+        };
+        state.rematch();
+        state
+    }
+
+    fn shown(state: &State) -> Vec<&str> {
+        state
+            .matches
+            .iter()
+            .map(|(i, _)| state.lines[*i].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn no_query_keeps_the_order_lines_came_in() {
+        let state = state(&["b/zeta", "a/alpha", "c"], "");
+        assert_eq!(shown(&state), ["b/zeta", "a/alpha", "c"]);
+    }
+
+    #[test]
+    fn best_match_first() {
+        let state = state(&["x/mxaxixn", "src/main", "x/domain", "lib"], "main");
+        assert_eq!(shown(&state), ["src/main", "x/domain", "x/mxaxixn"]);
+    }
+
+    #[test]
+    fn ties_go_to_the_shorter_line_then_the_earlier_one() {
+        let state = state(&["a/main/long", "b/main/x", "c/main/y"], "main");
+        assert_eq!(shown(&state), ["b/main/x", "c/main/y", "a/main/long"]);
+    }
+
+    #[test]
+    fn cursor_stays_in_range() {
+        let mut state = state(&["one", "two", "three"], "");
+        state.cursor = 2;
+        state.query = "one".to_string();
+        state.rematch();
+        assert_eq!(state.cursor, 0);
+    }
+
+    #[test]
+    fn reorder_keeps_the_highlighted_and_marked_lines() {
+        let mut state = state(&["a", "b", "c", "d"], "");
+        state.cursor = 1;
+        state.marked.insert(2);
+        state.reorder(&["c".to_string(), "b".to_string()]);
+        assert_eq!(state.lines, ["c", "b", "a", "d"]);
+        assert_eq!(state.lines[state.matches[state.cursor].0], "b");
+        assert_eq!(state.marked.iter().collect::<Vec<_>>(), [&0]);
+    }
+}
@@ -0,0 +1,266 @@
+// standard library
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write as _;
+use std::fs::{create_dir_all, read_dir, remove_dir_all, rename, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+// own
+use crate::config;
+use crate::detect::DETECTORS;
+use crate::flags::Flags;
+use crate::utils::{format_log, read_lines, Failure, LogLevel, StringExt};
+
+/// how long a preview is shown from the cache before it is made again
+const MAX_AGE: Duration = Duration::from_secs(30);
+/// how many of the latest commits are shown
+const COMMITS: &str = "5";
+/// how many lines of the README are shown
+const README_LINES: usize = 20;
+
+/// Prints the branch, how far it is ahead of and behind its upstream, how
+/// many files are changed, the latest commits and the start of the README of
+/// the repo at `arg`, which is what fzf shows next to the list.
+pub fn execute(arg: Option<&str>, flags: &Flags) -> Result<(), Failure> {
+    let path = PathBuf::from(arg.ok_or(format_log(
+        LogLevel::Error,
+        "missing path for 'preview'".to_string(),
+    ))?);
+    let settings = config::read_settings(flags)?;
+
+    // moving through the list runs this once per line, so what was made a
+    // moment ago is shown again instead of asking git every time
+    let cached_path = cached_path(&path)?;
+    if settings.cache {
+        if let Some(preview) = read_cached(&cached_path) {
+            print!("{}", preview);
+            return Ok(());
+        }
+    }
+
+    let preview = render(&path);
+    print!("{}", preview);
+    if settings.cache {
+        // a preview that could not be cached is still shown
+        _ = write_cached(&cached_path, &preview);
+    }
+    Ok(())
+}
+
+/// where the preview of `path` is cached, named after a hash of it
+fn cached_path(path: &Path) -> Result<PathBuf, String> {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    Ok(preview_dir()?.join(format!("{:016x}.txt", hasher.finish())))
+}
+
+/// the directory previews are cached in, next to cache.txt
+fn preview_dir() -> Result<PathBuf, String> {
+    Ok(config::config_dir()?.join("preview"))
+}
+
+fn read_cached(cached_path: &Path) -> Option<String> {
+    let age = cached_path
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+    match age < MAX_AGE {
+        true => std::fs::read_to_string(cached_path).ok(),
+        false => None,
+    }
+}
+
+/// fzf kills previews of lines that were already scrolled past, so each one
+/// is written to its own file first to never leave half a preview behind
+fn write_cached(cached_path: &Path, preview: &str) -> std::io::Result<()> {
+    create_dir_all(cached_path.parent().unwrap_or(Path::new(".")))?;
+    let temp_file_path = cached_path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp_file_path, preview)?;
+    rename(&temp_file_path, cached_path)
+}
+
+/// deletes every cached preview
+pub fn clear() -> Result<(), String> {
+    match remove_dir_all(preview_dir()?) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format_log(
+            LogLevel::Error,
+            format!("Failed to remove directory \"preview\": {}", e),
+        )),
+    }
+}
+
+fn render(path: &Path) -> String {
+    let mut preview = String::new();
+    let Some((vcs, kind)) = DETECTORS
+        .iter()
+        .find_map(|d| d.detect(path).map(|kind| (d.name(), kind)))
+    else {
+        _ = writeln!(preview, "{} is not a repo", path.display());
+        return preview;
+    };
+    _ = writeln!(preview, "{}", path.display().to_string().bold().underline());
+    _ = writeln!(preview, "{} {}\n", vcs, kind.name());
+
+    // a colocated jj checkout is a git repo too
+    if vcs == "git" || path.join(".git").exists() {
+        git_status(path, &mut preview);
+        git_log(path, &mut preview);
+    }
+    readme(path, &mut preview);
+    preview
+}
+
+/// what `git` prints, or `None` if it failed or is not installed
+pub fn git(path: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// the branch, ahead/behind and a count of each kind of change
+fn git_status(path: &Path, preview: &mut String) {
+    // bare repos have no work tree to have a status
+    let Some(status) = git(path, &["status", "--porcelain=v2", "--branch"]) else {
+        return;
+    };
+
+    let mut branch = String::new();
+    let mut ahead_behind = None;
+    let (mut staged, mut modified, mut untracked, mut conflicted) = (0, 0, 0, 0);
+    for line in status.lines() {
+        let mut fields = line.split(' ');
+        match (fields.next(), fields.next()) {
+            (Some("#"), Some("branch.oid")) if branch.is_empty() => {
+                branch = fields.next().unwrap_or_default().chars().take(7).collect();
+            }
+            (Some("#"), Some("branch.head")) => match fields.next() {
+                Some("(detached)") | None => {}
+                Some(head) => branch = head.to_string(),
+            },
+            (Some("#"), Some("branch.ab")) => {
+                ahead_behind = fields.next().zip(fields.next());
+            }
+            (Some("1" | "2"), Some(xy)) => {
+                staged += xy.starts_with(|c| c != '.') as u32;
+                modified += xy.ends_with(|c| c != '.') as u32;
+            }
+            (Some("u"), _) => conflicted += 1,
+            (Some("?"), _) => untracked += 1,
+            _ => {}
+        }
+    }
+
+    _ = write!(preview, "{} {}", "branch:".to_string().bold(), branch);
+    if let Some((ahead, behind)) = ahead_behind {
+        // `+1 -2` as git prints it
+        _ = write!(
+            preview,
+            " (ahead {}, behind {})",
+            ahead.trim_start_matches('+'),
+            behind.trim_start_matches('-')
+        );
+    }
+    _ = writeln!(preview);
+
+    let changes: Vec<String> = [
+        (staged, "staged"),
+        (modified, "modified"),
+        (untracked, "untracked"),
+        (conflicted, "conflicted"),
+    ]
+    .into_iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, name)| format!("{} {}", count, name))
+    .collect();
+    let changes = match changes.is_empty() {
+        true => "clean".to_string(),
+        false => changes.join(", "),
+    };
+    _ = writeln!(preview, "{} {}\n", "changes:".to_string().bold(), changes);
+}
+
+fn git_log(path: &Path, preview: &mut String) {
+    // fails on a repo without commits yet
+    let Some(log) = git(
+        path,
+        &[
+            "log",
+            "-n",
+            COMMITS,
+            "--color=always",
+            "--format=%C(yellow)%h%C(reset) %s %C(dim)(%cr)%C(reset)",
+        ],
+    ) else {
+        return;
+    };
+    _ = writeln!(preview, "{}", "commits:".to_string().bold());
+    _ = writeln!(preview, "{}\n", log.trim_end());
+}
+
+/// the first lines of the first file named like README, in any case
+fn readme(path: &Path, preview: &mut String) {
+    let Some(readme) = read_dir(path).ok().and_then(|entries| {
+        entries.filter_map(Result::ok).map(|e| e.path()).find(|p| {
+            p.is_file()
+                && p.file_stem()
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case("readme"))
+        })
+    }) else {
+        return;
+    };
+    let Ok(file) = File::open(&readme) else {
+        return;
+    };
+    let name = readme.file_name().unwrap_or_default().to_string_lossy();
+    _ = writeln!(preview, "{}", format!("{}:", name).bold());
+    for line in read_lines(BufReader::new(file)).take(README_LINES) {
+        _ = writeln!(preview, "{}", line);
+    }
+}
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "preview"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "preview".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!(
+        "{}prints the branch, changes, latest commits and README",
+        title
+    );
+    println!(
+        "{}of a repo, which fzf shows next to the list\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!("{}", "sgr preview path/to/repo".to_string().fill_left(17));
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. fzf and sk show it by themselves; set \"preview = false\" in config.txt");
+        println!("     to turn it off");
+        println!("  2. Previews are cached in the \"preview\" directory next to dirs.txt for");
+        println!("     30 seconds so moving through the list stays fast");
+        println!("  3. Branch, ahead/behind, changes and commits are only shown for git");
+    }
+}
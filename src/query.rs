@@ -0,0 +1,242 @@
+// standard library
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::time::SystemTime;
+
+// own
+use crate::discover::{Repo, Report};
+use crate::flags::Flags;
+use crate::fuzzy::{self, Match};
+use crate::utils::{format_log, Exit, Failure, LogLevel, StringExt};
+use crate::{action, config, history, run};
+
+/// how many times over a keyword matching a repo's own directory name counts
+/// compared to one matching the directories above it
+const NAME_WEIGHT: i64 = 3;
+/// extra for a keyword that is the whole directory name
+const EXACT: i64 = 32;
+
+/// how well the keywords fit a repo
+struct Score {
+    points: i64,
+    /// every keyword matched in one piece and at least one matched the name
+    confident: bool,
+}
+
+/// Prints the repo best matching `keywords` without asking, unless it is not
+/// clearly the best one; then the picker is opened with the keywords typed in.
+pub fn execute(keywords: Option<&str>, flags: &Flags) -> Result<String, Failure> {
+    let query = keywords.ok_or(format_log(
+        LogLevel::Error,
+        "missing keywords for 'query'".to_string(),
+    ))?;
+    let roots = config::read_roots(flags)?;
+    let settings = config::read_settings(flags)?;
+    let report = Report::default();
+    let repos = run::find(&roots, &settings, &report, run::indexed(flags), |_| true);
+    report.print(&roots, &settings);
+
+    let now = SystemTime::now();
+    let frecencies: HashMap<_, _> = match settings.frecency {
+        true => history::load()?
+            .into_iter()
+            .map(|entry| (entry.path.clone(), entry.frecency(now)))
+            .collect(),
+        false => HashMap::new(),
+    };
+    let frecency = |repo: &Repo| frecencies.get(&repo.path).copied().unwrap_or(0);
+
+    let keywords: Vec<&str> = query.split_whitespace().collect();
+    let mut ranked: Vec<(&Repo, Score)> = repos
+        .iter()
+        .filter_map(|repo| {
+            let root = roots.get(repo.root)?;
+            let name = repo.path.file_name()?.to_string_lossy();
+            let parent = repo
+                .path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(&root.path).ok())
+                .map(|parent| parent.to_string_lossy())
+                .unwrap_or_default();
+            score(&keywords, &name, &parent).map(|score| (repo, score))
+        })
+        .collect();
+    // then the ones jumped to more, then shorter paths, like fzf
+    ranked.sort_by(|(a, a_score), (b, b_score)| {
+        b_score
+            .points
+            .cmp(&a_score.points)
+            .then_with(|| frecency(b).cmp(&frecency(a)))
+            .then_with(|| a.path.as_os_str().len().cmp(&b.path.as_os_str().len()))
+    });
+
+    let (best, best_score) = ranked.first().ok_or(Failure::new(
+        Exit::NoRepos,
+        format_log(LogLevel::Error, format!("no repo matches '{}'", query)),
+    ))?;
+    let tied = ranked.get(1).is_some_and(|(second, second_score)| {
+        second_score.points == best_score.points && frecency(second) == frecency(best)
+    });
+    // scripts get the best guess since there is nobody to ask
+    if (best_score.confident && !tied) || !std::io::stdin().is_terminal() {
+        run::remember(&best.path);
+        let best = [(*best).clone()];
+        return action::run(settings.action, &best, flags, run::output);
+    }
+
+    let (picked, action) = run::select(flags, query, Some(repos))?;
+    action::run(action, &picked, flags, run::output)
+}
+
+/// Every keyword has to match either the repo's directory name or the
+/// directories between it and its root, whichever fits it better. `None` if
+/// one of them matches neither.
+fn score(keywords: &[&str], name: &str, parent: &str) -> Option<Score> {
+    let mut score = Score {
+        points: 0,
+        confident: false,
+    };
+    let mut in_one_piece = true;
+
+    for keyword in keywords {
+        let in_name = fuzzy::fuzzy_match(keyword, name).map(|m| {
+            let exact = match name.to_lowercase() == keyword.to_lowercase() {
+                true => EXACT,
+                false => 0,
+            };
+            ((m.score + exact) * NAME_WEIGHT, m)
+        });
+        let in_parent = fuzzy::fuzzy_match(keyword, parent).map(|m| (m.score, m));
+        let (points, m, is_name) = match (in_name, in_parent) {
+            (Some((name_points, name_match)), Some((parent_points, parent_match))) => {
+                match name_points >= parent_points {
+                    true => (name_points, name_match, true),
+                    false => (parent_points, parent_match, false),
+                }
+            }
+            (Some((points, m)), None) => (points, m, true),
+            (None, Some((points, m))) => (points, m, false),
+            (None, None) => return None,
+        };
+
+        score.points += points;
+        score.confident |= is_name;
+        in_one_piece &= is_contiguous(&m);
+    }
+    score.confident &= in_one_piece;
+    Some(score)
+}
+
+fn is_contiguous(m: &Match) -> bool {
+    match (m.positions.first(), m.positions.last()) {
+        (Some(first), Some(last)) => last - first + 1 == m.positions.len(),
+        _ => true,
+    }
+}
+
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "query"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "query".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!("{}jumps to the repo best matching the keywords", title);
+    println!(
+        "{}without opening the picker\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!(
+            "{}Keywords matching a repo's own directory name count more than",
+            "".to_string().pad_right(15).fill_left(2)
+        );
+        println!(
+            "{}ones matching the directories above it",
+            "".to_string().pad_right(15).fill_left(2)
+        );
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!("{}", "sgr query sgr".to_string().fill_left(17));
+        println!("{}", "sgr query work api".to_string().fill_left(17));
+        println!(
+            "{}",
+            "sgr query api --action code".to_string().fill_left(17)
+        );
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. If several repos match equally well, or the best one only matches");
+        println!("     scattered letters, the picker opens with the keywords typed in instead");
+        println!("  2. Without a terminal, like in scripts, the best match is always printed");
+        println!("  3. Exits with 4 if no repo matches");
+        println!("  4. \"--action\" works as it does for \"run\"");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(keywords: &str, name: &str, parent: &str) -> Option<i64> {
+        let keywords: Vec<&str> = keywords.split_whitespace().collect();
+        score(&keywords, name, parent).map(|score| score.points)
+    }
+
+    fn confident(keywords: &str, name: &str, parent: &str) -> bool {
+        let keywords: Vec<&str> = keywords.split_whitespace().collect();
+        score(&keywords, name, parent).unwrap().confident
+    }
+
+    #[test]
+    fn name_counts_more_than_parent() {
+        let in_name = points("web", "web", "work").unwrap();
+        let in_parent = points("web", "api", "web").unwrap();
+        assert!(in_name > in_parent);
+        assert!(points("web", "website", "") > points("web", "api", "web"));
+    }
+
+    #[test]
+    fn exact_name_wins() {
+        assert!(points("sugar", "sugar", "") > points("sugar", "sugary", ""));
+        assert!(points("Sugar", "Sugar", "") > points("Sugar", "Sugary", ""));
+        assert_eq!(
+            points("sugar", "sugar", "").unwrap() - points("sugar", "sugary", "").unwrap(),
+            EXACT * NAME_WEIGHT
+        );
+    }
+
+    #[test]
+    fn every_keyword_has_to_match_somewhere() {
+        assert!(points("work sugar", "sugar", "work").is_some());
+        assert!(points("home sugar", "sugar", "work").is_none());
+        assert!(points("zz", "sugar", "work").is_none());
+    }
+
+    #[test]
+    fn keyword_takes_the_better_of_name_and_parent() {
+        let both = points("sugar", "sugar", "sugar").unwrap();
+        assert_eq!(Some(both), points("sugar", "sugar", ""));
+    }
+
+    #[test]
+    fn confident_only_in_one_piece_and_on_a_name() {
+        assert!(confident("sug", "sugar", "work"));
+        assert!(confident("work sug", "sugar", "work"));
+        // only the directories above matched
+        assert!(!confident("work", "sugar", "work"));
+        // scattered over the name
+        assert!(!confident("sgr", "sugar", "work"));
+        assert!(!confident("sug wk", "sugar", "work"));
+    }
+
+    #[test]
+    fn contiguous() {
+        let contiguous = |query, line| is_contiguous(&fuzzy::fuzzy_match(query, line).unwrap());
+        assert!(contiguous("gar", "sugar"));
+        assert!(!contiguous("sgr", "sugar"));
+        assert!(contiguous("", "sugar"));
+    }
+}
@@ -4,6 +4,7 @@ use std::io::{BufRead, BufReader, BufWriter, Seek, Write};
 use std::path::Path;
 
 // own
+use crate::config::{self, Root};
 use crate::list;
 use crate::utils::{format_log, LogLevel, PathExt, StrExt, StringExt};
 
@@ -18,16 +19,7 @@ pub fn execute(arg: Option<&str>) -> Result<(), String> {
     ))?;
 
     // open files and initialze readers, writers
-    let file_path = std::env::current_exe()
-        .map_err(|e| {
-            format_log(
-                LogLevel::Error,
-                format!("Failed to get sgr.exe path: {}", e),
-            )
-        })?
-        .parent()
-        .ok_or("Failed to get parent directory of sgr.exe")?
-        .join("dirs.txt");
+    let file_path = config::dirs_txt_path()?;
     let mut file = OpenOptions::new()
         .read(true)
         .open(&file_path)
@@ -155,7 +147,14 @@ pub fn execute(arg: Option<&str>) -> Result<(), String> {
         let trimmed_path = abs_path.strip_prefix(r#"\\?\"#).unwrap_or(&abs_path);
 
         lines
-            .filter(|line| !line.trim().eq_ignore_ascii_case(&trimmed_path))
+            .filter(|line| match Root::parse(line) {
+                Ok(root) => !root
+                    .path
+                    .display()
+                    .to_string()
+                    .eq_ignore_ascii_case(trimmed_path),
+                Err(_) => !line.trim().eq_ignore_ascii_case(trimmed_path),
+            })
             .try_for_each(|line| {
                 writeln!(writer, "{}", line).map_err(|e| {
                     format_log(
@@ -0,0 +1,194 @@
+// standard library
+use std::path::PathBuf;
+
+// own
+use crate::config::Root;
+use crate::detect::Kind;
+use crate::discover::Repo;
+use crate::preview;
+use crate::utils::{format_log, LogLevel};
+
+/// something the picker shows about each repo, in its own aligned column
+#[derive(Clone, Copy, PartialEq)]
+pub enum Column {
+    /// the repo's directory name
+    Name,
+    /// the label of the directory in dirs.txt it was found in
+    Root,
+    /// the checked out branch, or commit if there is none
+    Branch,
+    /// `*` if there are uncommitted changes
+    Status,
+    /// the vcs and kind, like `git clone`
+    Vcs,
+    /// the path from its root to the repo
+    Path,
+}
+
+impl Column {
+    pub const ALL: [Column; 6] = [
+        Column::Name,
+        Column::Root,
+        Column::Branch,
+        Column::Status,
+        Column::Vcs,
+        Column::Path,
+    ];
+
+    /// without `status`, which runs git for every repo
+    pub const DEFAULT: [Column; 5] = [
+        Column::Name,
+        Column::Root,
+        Column::Branch,
+        Column::Vcs,
+        Column::Path,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Root => "root",
+            Column::Branch => "branch",
+            Column::Status => "status",
+            Column::Vcs => "vcs",
+            Column::Path => "path",
+        }
+    }
+
+    /// how wide it is unless it is the last one, which is never cut off
+    fn width(&self) -> usize {
+        match self {
+            Column::Name => 24,
+            Column::Root => 12,
+            Column::Branch => 20,
+            Column::Status => 1,
+            Column::Vcs => 15,
+            Column::Path => 40,
+        }
+    }
+
+    /// comma separated column names, like `name,branch,path`, in order
+    pub fn parse_list(list: &str) -> Result<Vec<Column>, String> {
+        let columns = list
+            .split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                Column::ALL
+                    .into_iter()
+                    .find(|column| column.name() == name)
+                    .ok_or(format_log(
+                        LogLevel::Error,
+                        format!(
+                            "unknown column '{}'; expected name, root, branch, status, vcs or path",
+                            name
+                        ),
+                    ))
+            })
+            .collect::<Result<Vec<Column>, String>>()?;
+        match columns.is_empty() {
+            true => Err(format_log(
+                LogLevel::Error,
+                "'columns' in config.txt needs at least one column".to_string(),
+            )),
+            false => Ok(columns),
+        }
+    }
+
+    /// whether showing it runs git, like `dirty` does
+    pub fn needs_status(&self) -> bool {
+        matches!(self, Column::Status)
+    }
+}
+
+/// `repo`'s `columns`, each but the last cut off or padded to its width
+pub fn row(repo: &Repo, roots: &[Root], columns: &[Column]) -> String {
+    let root = roots.get(repo.root);
+    let cells: Vec<String> = columns
+        .iter()
+        .map(|column| match column {
+            Column::Name => repo
+                .path
+                .file_name()
+                .unwrap_or(repo.path.as_os_str())
+                .to_string_lossy()
+                .to_string(),
+            Column::Root => root.map(Root::label).unwrap_or_default(),
+            Column::Branch => branch(repo).unwrap_or("-".to_string()),
+            Column::Status => match dirty(repo) {
+                Some(true) => "*".to_string(),
+                _ => " ".to_string(),
+            },
+            Column::Vcs => format!("{} {}", repo.vcs, repo.kind.name()),
+            Column::Path => match root.and_then(|root| repo.path.strip_prefix(&root.path).ok()) {
+                Some(path) if path.as_os_str().is_empty() => ".".to_string(),
+                Some(path) => path.display().to_string(),
+                None => repo.path.display().to_string(),
+            },
+        })
+        .collect();
+
+    let last = cells.len().saturating_sub(1);
+    cells
+        .iter()
+        .zip(columns)
+        .enumerate()
+        .map(|(i, (cell, column))| match i == last {
+            true => cell.clone(),
+            false => fit(cell, column.width()),
+        })
+        .collect::<Vec<String>>()
+        .join("  ")
+        .trim_end()
+        .to_string()
+}
+
+/// `text` padded to `width` chars, or cut off with `…` if it is longer
+fn fit(text: &str, width: usize) -> String {
+    match text.chars().count() {
+        len if len > width => {
+            let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+            format!("{}…", cut)
+        }
+        len => format!("{}{}", text, " ".repeat(width - len)),
+    }
+}
+
+/// where git keeps `repo`'s HEAD, for git and colocated jj repos
+fn git_dir(repo: &Repo) -> Option<PathBuf> {
+    if repo.kind == Kind::Bare {
+        return Some(repo.path.clone());
+    }
+    let dot_git = repo.path.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    // worktrees and submodules point to theirs
+    let contents = std::fs::read_to_string(&dot_git).ok()?;
+    Some(repo.path.join(contents.strip_prefix("gitdir:")?.trim()))
+}
+
+/// read from the vcs' files instead of running it, since this is done for
+/// every repo
+fn branch(repo: &Repo) -> Option<String> {
+    if repo.vcs == "hg" {
+        let branch = std::fs::read_to_string(repo.path.join(".hg").join("branch"));
+        return Some(branch.map_or("default".to_string(), |b| b.trim().to_string()));
+    }
+    let head = std::fs::read_to_string(git_dir(repo)?.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: refs/heads/") {
+        Some(branch) => Some(branch.to_string()),
+        // detached
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// whether a git checkout has uncommitted changes; `None` for other repos or
+/// if git failed
+fn dirty(repo: &Repo) -> Option<bool> {
+    if repo.kind == Kind::Bare || git_dir(repo).is_none() {
+        return None;
+    }
+    preview::git(&repo.path, &["status", "--porcelain"]).map(|status| !status.is_empty())
+}
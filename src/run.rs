@@ -1,77 +1,365 @@
 // standard library
-use std::fs::OpenOptions;
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-
-// third party
-use walkdir::WalkDir;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 
 // own
-use crate::utils::{format_log, LogLevel};
-
-pub fn execute() -> Result<String, String> {
-    let dirs_txt_path = std::env::current_exe()
-        .map_err(|e| {
-            format_log(
-                LogLevel::Error,
-                format!("Failed to get sgr.exe path: {}", e),
-            )
-        })?
-        .parent()
-        .ok_or("Failed to get parent directory of sgr.exe")?
-        .join("dirs.txt");
-
-    let file = OpenOptions::new()
-        .read(true)
-        .open(&dirs_txt_path)
-        .map_err(|e| {
-            format_log(
-                LogLevel::Error,
-                format!("Failed to open file \"{:?}\": {}", dirs_txt_path, e),
-            )
-        })?;
-
-    let reader = BufReader::new(file);
-    let lines = reader
-        .lines()
-        .filter_map(|line| line.ok())
-        .map(|line| PathBuf::from(line));
-
-    let mut fzf_process = Command::new("fzf")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .map_err(|e| format_log(LogLevel::Error, format!("failed to start fzf: {}", e)))?;
-
-    if let Some(stdin) = fzf_process.stdin.as_mut() {
-        lines.for_each(|path| {
-            WalkDir::new(&path)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| {
-                    e.file_type().is_dir()
-                        && e.path().ends_with(".git")
-                        && e.path().join("HEAD").exists()
-                })
-                .for_each(|e| {
-                    let git_repo = e.path().parent().unwrap_or(e.path());
-                    writeln!(stdin, "{}", git_repo.display()).unwrap_or(());
-                })
+use crate::action::{self, Action};
+use crate::config::{Root, Settings};
+use crate::discover::{Repo, Report};
+use crate::flags::Flags;
+use crate::format::{self, Format};
+use crate::rows::{self, Column};
+use crate::selector::{self, Backend, Fzf};
+use crate::utils::{format_log, Exit, Failure, LogLevel, StringExt};
+use crate::{cache, config, daemon, discover, history, picker};
+
+pub fn execute(query: Option<&str>, flags: &Flags) -> Result<String, Failure> {
+    let (repos, action) = select(flags, query.unwrap_or_default(), None)?;
+    action::run(action, &repos, flags, output)
+}
+
+/// the paths of `repos` one per line, or each ended by a NUL with `--print0`,
+/// as records with `--format`
+pub fn output(repos: &[Repo], flags: &Flags) -> String {
+    let format = flags.format.unwrap_or(Format::Plain);
+    // only records have more than the path; `root` of a repo is its index
+    // in these, which are read the same way as when it was found
+    let roots = match format {
+        Format::Plain => Vec::new(),
+        _ => config::read_roots(flags).unwrap_or_default(),
+    };
+    let records: Vec<String> = repos
+        .iter()
+        .map(|repo| format::repo(repo, &roots, format))
+        .collect();
+    format::join(&records, flags)
+}
+
+/// Opens the picker with `query` typed in already, on `repos` if they were
+/// found already or on repos as they are found otherwise. Returns the repos
+/// that were picked, which is only ever one without `--multi`, and what to
+/// do with them as the key they were picked with says.
+pub fn select(
+    flags: &Flags,
+    query: &str,
+    repos: Option<Vec<Repo>>,
+) -> Result<(Vec<Repo>, Action), Failure> {
+    let roots = Arc::new(config::read_roots(flags)?);
+    let settings = Arc::new(config::read_settings(flags)?);
+    let report = Arc::new(Report::default());
+
+    // fzf is only required when asked for by name
+    let backend = match &settings.selector {
+        Some(selector) => selector.backend(),
+        None => Some(&Fzf as &dyn Backend),
+    };
+    let mut hide_path =
+        settings.status && backend.is_some_and(|backend| !backend.hide_path().is_empty());
+    let spawned = match backend.map(|backend| {
+        let spawned = selector::spawn(backend, query, &settings, hide_path, flags.multi);
+        (backend, spawned)
+    }) {
+        Some((backend, Ok(child))) => Some((backend, child)),
+        Some((_, Err(_))) if settings.selector.is_none() => None,
+        Some((_, Err(e))) => return Err(e),
+        None => None,
+    };
+    // the built in picker shows whole lines
+    hide_path &= spawned.is_some();
+
+    // feed the picker as repos are found instead of after the whole walk
+    let (sender, lines) = mpsc::channel();
+    let indexed = repos.or_else(|| indexed(flags));
+    let paths = Arc::new(Mutex::new(HashMap::new()));
+    // every repo shown, by its path, to give back the picked ones as found
+    let shown = Arc::new(Mutex::new(HashMap::new()));
+    // the search finished without finding anything
+    let none_found = Arc::new(AtomicBool::new(false));
+    // the rows once the search is done, in an order that does not depend on
+    // which thread found what first
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let scan = {
+        let (roots, settings, report, paths, shown, none_found, order) = (
+            roots.clone(),
+            settings.clone(),
+            report.clone(),
+            paths.clone(),
+            shown.clone(),
+            none_found.clone(),
+            order.clone(),
+        );
+        move || {
+            // repos from history come first and are found again later
+            let emitted = Mutex::new(HashSet::new());
+            let ranked = match settings.frecency {
+                true => history::ranked(&roots, &settings),
+                false => Vec::new(),
+            };
+            // rows by the path of their repo
+            let made = Mutex::new(HashMap::new());
+            // once the picker is closed there is no point in scanning further
+            let closed = AtomicBool::new(false);
+            let (queue, queued) = mpsc::channel::<Repo>();
+            let queued = Mutex::new(queued);
+            // the status of every repo is looked up by running git, so rows
+            // are made on several threads then
+            let workers = match settings.status && settings.columns.iter().any(Column::needs_status)
+            {
+                true => std::thread::available_parallelism().map_or(1, |n| n.get()),
+                false => 1,
+            };
+
+            let found = std::thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(|| loop {
+                        let repo = match queued.lock() {
+                            Ok(queued) => queued.recv(),
+                            Err(_) => break,
+                        };
+                        let Ok(repo) = repo else {
+                            break;
+                        };
+                        let line = line(&repo, &roots, &settings, hide_path, &paths);
+                        if let Ok(mut made) = made.lock() {
+                            made.insert(repo.path.clone(), line.clone());
+                        }
+                        if let Ok(mut shown) = shown.lock() {
+                            shown.insert(repo.path.clone(), repo);
+                        }
+                        if sender.send(line).is_err() {
+                            closed.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                    });
+                }
+
+                // dropped at the end so the workers stop once the queue is empty
+                let queue = queue;
+                let emit = |repo: &Repo| {
+                    let first_time = emitted
+                        .lock()
+                        .is_ok_and(|mut emitted| emitted.insert(repo.path.clone()));
+                    !closed.load(Ordering::Relaxed)
+                        && (!first_time || queue.send(repo.clone()).is_ok())
+                };
+                match ranked.iter().all(emit) {
+                    true => find(&roots, &settings, &report, indexed, emit),
+                    false => Vec::new(),
+                }
+            });
+            none_found.store(
+                emitted.lock().is_ok_and(|emitted| emitted.is_empty()),
+                Ordering::Relaxed,
+            );
+            // history first like they came, then sorted like `find` returns them
+            let mut made = made.into_inner().unwrap_or_default();
+            if let Ok(mut order) = order.lock() {
+                *order = ranked
+                    .iter()
+                    .chain(&found)
+                    .filter_map(|repo| made.remove(&repo.path))
+                    .collect();
+            }
+        }
+    };
+
+    // The walk stops by itself once it is out of time, but a read stuck on a
+    // dead network mount never returns to let it. So it runs on its own
+    // thread, which is left behind if it is still going by then.
+    std::thread::spawn(scan);
+    let selected = match spawned {
+        Some((backend, child)) => selector::pick_with(backend, child, lines, &settings, &report)?,
+        None => picker::pick(lines, &order, query, flags.multi, &settings, &report)?,
+    };
+    // only now so it does not draw over the picker
+    report.print(&roots, &settings);
+    // the picker stops waiting for the search once it is out of time, so
+    // nothing shown by then is nothing found as well
+    if report.timed_out() && shown.lock().is_ok_and(|shown| shown.is_empty()) {
+        none_found.store(true, Ordering::Relaxed);
+    }
+
+    let picked = match selected {
+        Some(picked) => picked,
+        None if none_found.load(Ordering::Relaxed) => {
+            return Err(Failure::new(
+                Exit::NoRepos,
+                format_log(
+                    LogLevel::Error,
+                    "no repos found in the directories in dirs.txt".to_string(),
+                ),
+            ))
+        }
+        None => {
+            return Err(Failure::new(
+                Exit::Cancelled,
+                format_log(LogLevel::Warn, "Did not choose a directory".to_string()),
+            ))
+        }
+    };
+    // rows shown without their path in front are looked up in there
+    let paths = paths.lock().ok();
+    let shown = shown.lock().ok();
+    let selected: Vec<Repo> = picked
+        .lines
+        .into_iter()
+        .map(|selected| {
+            let row_path = paths.as_ref().and_then(|paths| paths.get(&selected));
+            let path = match (row_path, selected.split_once('\t')) {
+                (Some(path), _) => path.clone(),
+                (None, Some((path, _))) => PathBuf::from(path),
+                (None, None) => PathBuf::from(&selected),
+            };
+            // `selector-command` may print something of its own
+            shown
+                .as_ref()
+                .and_then(|shown| shown.get(&path).cloned())
+                .or_else(|| discover::repo_at(&roots, &settings, &path))
+                .ok_or(Failure::from(format_log(
+                    LogLevel::Error,
+                    format!("'{}' is not one of the repos listed", selected),
+                )))
         })
+        .collect::<Result<Vec<Repo>, Failure>>()?;
+    for repo in &selected {
+        remember(&repo.path);
     }
+    let action = picked
+        .key
+        .and_then(|picked| {
+            let mut keys = settings.action_keys.iter();
+            keys.find(|(key, _)| key.name() == picked)
+        })
+        .map_or(settings.action, |(_, action)| *action);
+    Ok((selected, action))
+}
 
-    let output = fzf_process
-        .wait_with_output()
-        .map_err(|e| format_log(LogLevel::Error, format!("Failed to wait for fzf: {}", e)))?;
+/// What the picker shows for `repo`. A plain line is its path, a tab, then
+/// its vcs and kind, so those can be fuzzy matched too and cut off again
+/// after picking. A row of columns starts with the path and a tab the same
+/// way if the picker hides it, or else it is kept in `paths` to look the
+/// path up by.
+fn line(
+    repo: &Repo,
+    roots: &[Root],
+    settings: &Settings,
+    hide_path: bool,
+    paths: &Mutex<HashMap<String, PathBuf>>,
+) -> String {
+    if !settings.status {
+        return format!("{}\t{} {}", repo.path.display(), repo.vcs, repo.kind.name());
+    }
+    let row = rows::row(repo, roots, &settings.columns);
+    if hide_path {
+        return format!("{}\t{}", repo.path.display(), row);
+    }
+
+    let Ok(mut paths) = paths.lock() else {
+        return row;
+    };
+    // like two repos of the same name when only names are shown
+    let row = match paths.get(&row) {
+        Some(path) if path != &repo.path => format!("{}  {}", row, repo.path.display()),
+        _ => row,
+    };
+    paths.insert(row.clone(), repo.path.clone());
+    row
+}
+
+/// `indexed` if there are repos in it, or else what `cache::scan` or
+/// `discover::scan` finds, passing each repo to `on_hit` as it comes
+pub fn find<F>(
+    roots: &[Root],
+    settings: &Settings,
+    report: &Report,
+    indexed: Option<Vec<Repo>>,
+    on_hit: F,
+) -> Vec<Repo>
+where
+    F: Fn(&Repo) -> bool + Sync,
+{
+    match indexed {
+        Some(repos) => {
+            _ = repos.iter().all(on_hit);
+            repos
+        }
+        None if settings.cache => cache::scan(roots, settings, report, on_hit),
+        None => discover::scan(roots, settings, report, on_hit),
+    }
+}
 
-    if !output.status.success() {
-        return Err(format_log(
-            LogLevel::Warn,
-            "Did not choose a directory".to_string(),
-        ));
+/// A running daemon already knows every repo. Flags can change what would be
+/// found though, so calls with those search by themselves.
+pub fn indexed(flags: &Flags) -> Option<Vec<Repo>> {
+    match flags.overrides_scan() {
+        true => None,
+        false => daemon::query(),
     }
+}
+
+/// records a jump in history.txt, which is not worth failing the jump over
+pub fn remember(path: &Path) {
+    // `record` errors already have their header
+    if let Err(e) = history::record(path) {
+        eprintln!("{}", e);
+    }
+}
 
-    let selected_path = String::from_utf8_lossy(&output.stdout).to_string();
-    return Ok(selected_path);
+pub fn help(verbose: bool) {
+    let title = match verbose {
+        true => "run"
+            .to_string()
+            .pad_right(15)
+            .fill_left(2)
+            .bold()
+            .underline(),
+        false => "run".to_string().pad_right(15).fill_left(2).bold(),
+    };
+
+    println!(
+        "{}opens the picker, with the keywords typed in if any",
+        title
+    );
+    println!(
+        "{}and prints the chosen repo; same as no command at all\n",
+        "".to_string().pad_right(15).fill_left(2)
+    );
+    if verbose {
+        println!("\n{}", "Usage:".to_string().bold().underline().fill_left(2));
+        println!("{}", "sgr".to_string().fill_left(17));
+        println!("{}", "sgr run".to_string().fill_left(17));
+        println!("{}", "sgr run work api".to_string().fill_left(17));
+        println!("{}", "sgr work api".to_string().fill_left(17));
+        println!(
+            "{}",
+            "sgr -- --height 40% --layout reverse"
+                .to_string()
+                .fill_left(17)
+        );
+        println!("{}", "sgr --multi --print0".to_string().fill_left(17));
+        println!(
+            "{}",
+            "sugar --multi --format json".to_string().fill_left(17)
+        );
+        println!("{}", "sgr --action editor api".to_string().fill_left(17));
+
+        println!("\n{}:", "Notes".to_string().bold().underline().fill_left(2));
+        println!("  1. Keywords that are not a command name can be given without \"run\"");
+        println!("  2. Everything after \"--\" is passed on to the selector, after");
+        println!("     \"selector-options\" in config.txt");
+        println!("  3. The built in picker and \"selector-command\" take no options");
+        println!("  4. With \"--multi\", Tab marks several repos, which are printed one per");
+        println!("     line, or each ended by a NUL with \"--print0\"");
+        println!("  5. \"--format json|tsv|plain\" prints the path, root, line of the root");
+        println!("     in dirs.txt, vcs and kind of each chosen repo as records for scripts");
+        println!("  6. \"--action\" does something else than cd with the chosen repos:");
+        println!("     print, editor, code, shell or command (\"action-command\" in config.txt)");
+        println!("  7. In fzf, sk and the built in picker, Enter does \"--action\" (cd unless");
+        println!("     given) and Ctrl-O, Ctrl-V, Ctrl-T, Ctrl-X and Ctrl-Y pick with the");
+        println!("     editor, code, shell, command and print actions (\"action-keys\");");
+        println!("     Ctrl-X only if \"action-command\" is set");
+    }
 }
@@ -0,0 +1,437 @@
+// standard library
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Instant;
+
+// own
+use crate::config::Settings;
+use crate::discover::Report;
+use crate::utils::{format_log, shell_command, shell_quote, Exit, Failure, LogLevel};
+
+/// a program repos are written to one per line, that prints the one picked
+pub trait Backend: Sync {
+    /// what `selector` in config.txt calls it
+    fn name(&self) -> &'static str;
+
+    /// the program along with the arguments it needs to read lines from
+    /// stdin and print the chosen one, starting out filtered by `query`
+    fn command(&self, query: &str) -> Command;
+
+    /// whether exiting with `code` means nothing was chosen, as opposed to
+    /// the program failing
+    fn is_cancelled(&self, code: Option<i32>) -> bool;
+
+    /// the arguments to show what `command` prints for the highlighted
+    /// repo's path next to the list, if it can
+    fn preview(&self, _command: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// the arguments to show only what comes after the first tab of each
+    /// line while still printing the whole line, if it can
+    fn hide_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// whether `selector-options` are passed on to it
+    fn takes_options(&self) -> bool {
+        true
+    }
+
+    /// the arguments to let several lines be picked, each printed on its own
+    /// line, or `None` if it cannot
+    fn multi(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// the arguments to print which of `keys` picked the lines on the line
+    /// before them, empty for Enter, if it can
+    fn expect(&self, _keys: &[String]) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// what was picked, and with which of the `action-keys` if not Enter
+pub struct Picked {
+    pub lines: Vec<String>,
+    pub key: Option<String>,
+}
+
+/// every built in backend, by name
+pub static BACKENDS: [&dyn Backend; 6] = [&Fzf, &Skim, &Fzy, &Peco, &Rofi, &Dmenu];
+
+/// how the user picks a repo in `run`
+#[derive(Clone)]
+pub enum Selector {
+    /// sgr's own picker
+    Builtin,
+    Backend(&'static dyn Backend),
+    /// `selector-command` in config.txt
+    Custom(Custom),
+}
+
+impl Selector {
+    pub fn parse(name: &str) -> Result<Selector, String> {
+        if name == "builtin" {
+            return Ok(Selector::Builtin);
+        }
+        BACKENDS
+            .into_iter()
+            .find(|backend| backend.name() == name)
+            .map(Selector::Backend)
+            .ok_or(format_log(
+                LogLevel::Error,
+                format!(
+                    "unknown selector '{}'; expected one of builtin, fzf, sk, fzy, peco, rofi, dmenu",
+                    name
+                ),
+            ))
+    }
+
+    /// the program to run, or `None` for the built in picker
+    pub fn backend(&self) -> Option<&dyn Backend> {
+        match self {
+            Selector::Builtin => None,
+            Selector::Backend(backend) => Some(*backend),
+            Selector::Custom(custom) => Some(custom),
+        }
+    }
+}
+
+/// Starts `backend` filtered by `query`, showing `sgr preview` next to the
+/// list if the `preview` setting is on and it can, and hiding the path in
+/// front of each line if `hide_path` is on. With `multi`, several lines can
+/// be picked. `selector-options` come last so they can change any of that.
+pub fn spawn(
+    backend: &dyn Backend,
+    query: &str,
+    settings: &Settings,
+    hide_path: bool,
+    multi: bool,
+) -> Result<Child, Failure> {
+    let mut command = backend.command(query);
+    if multi {
+        command.args(backend.multi().ok_or(format_log(
+            LogLevel::Error,
+            format!("{} cannot pick more than one repo", backend.name()),
+        ))?);
+    }
+    if let Some(preview_command) = settings.preview.then(preview_command).flatten() {
+        command.args(backend.preview(&preview_command));
+    }
+    if hide_path {
+        command.args(backend.hide_path());
+    }
+    command.args(backend.expect(&keys(settings)));
+    if backend.takes_options() {
+        command.args(&settings.selector_options);
+    }
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let exit = match e.kind() {
+                std::io::ErrorKind::NotFound => Exit::SelectorMissing,
+                _ => Exit::Error,
+            };
+            Failure::new(
+                exit,
+                format_log(
+                    LogLevel::Error,
+                    format!("failed to start {}: {}", backend.name(), e),
+                ),
+            )
+        })
+}
+
+/// the names of the `action-keys`, like fzf takes them
+fn keys(settings: &Settings) -> Vec<String> {
+    settings
+        .action_keys
+        .iter()
+        .map(|(key, _)| key.name())
+        .collect()
+}
+
+/// `sgr preview`, quoted for the shell fzf runs it with
+fn preview_command() -> Option<String> {
+    let exe = std::env::current_exe().ok()?.display().to_string();
+    Some(format!("{} preview", shell_quote(&exe)))
+}
+
+/// Writes `lines` to a spawned backend as they come until there are no more
+/// or the `timeout` setting runs out, then waits for what was chosen, one
+/// line each. `None` if it was cancelled or nothing was chosen.
+pub fn pick_with(
+    backend: &dyn Backend,
+    mut child: Child,
+    lines: Receiver<String>,
+    settings: &Settings,
+    report: &Report,
+) -> Result<Option<Picked>, Failure> {
+    let deadline = settings.timeout.map(|timeout| Instant::now() + timeout);
+    if let Some(mut stdin) = child.stdin.take() {
+        loop {
+            let line = match deadline {
+                Some(deadline) => {
+                    lines.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match line {
+                Ok(line) => {
+                    // the backend was closed
+                    if writeln!(stdin, "{}", line).is_err() {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    report.time_out();
+                    break;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        // dropping stdin closes it so the backend knows the list is complete
+    }
+
+    let output = child.wait_with_output().map_err(|e| {
+        format_log(
+            LogLevel::Error,
+            format!("Failed to wait for {}: {}", backend.name(), e),
+        )
+    })?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut stdout = stdout.lines();
+    // the line with the key comes first, even if it is empty
+    let key = match backend.expect(&keys(settings)).is_empty() {
+        true => None,
+        false => stdout.next().map(str::trim).filter(|key| !key.is_empty()),
+    };
+    let selected: Vec<String> = stdout
+        .map(|line| line.trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    match output.status.success() {
+        true if selected.is_empty() => Ok(None),
+        true => Ok(Some(Picked {
+            lines: selected,
+            key: key.map(str::to_string),
+        })),
+        false if backend.is_cancelled(output.status.code()) => Ok(None),
+        false => Err(Failure::new(
+            // the shell of `selector-command` could not find the program
+            match output.status.code() {
+                Some(127 | 9009) => Exit::SelectorMissing,
+                _ => Exit::Error,
+            },
+            format_log(
+                LogLevel::Error,
+                format!("{} failed: {}", backend.name(), output.status),
+            ),
+        )),
+    }
+}
+
+pub struct Fzf;
+
+impl Backend for Fzf {
+    fn name(&self) -> &'static str {
+        "fzf"
+    }
+
+    fn command(&self, query: &str) -> Command {
+        let mut command = Command::new("fzf");
+        command.args(["--query", query]);
+        command
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        // 1 is no match, 130 is Esc or Ctrl-C; 2 is an actual error
+        matches!(code, Some(1 | 130))
+    }
+
+    fn preview(&self, command: &str) -> Vec<String> {
+        // the path is the first tab separated field, which fzf quotes
+        vec![
+            "--delimiter".to_string(),
+            "\t".to_string(),
+            "--preview".to_string(),
+            format!("{} {{1}}", command),
+        ]
+    }
+
+    fn hide_path(&self) -> Vec<String> {
+        vec![
+            "--delimiter".to_string(),
+            "\t".to_string(),
+            "--with-nth".to_string(),
+            "2..".to_string(),
+        ]
+    }
+
+    fn multi(&self) -> Option<Vec<String>> {
+        Some(vec!["--multi".to_string()])
+    }
+
+    fn expect(&self, keys: &[String]) -> Vec<String> {
+        match keys.is_empty() {
+            true => Vec::new(),
+            false => vec![format!("--expect={}", keys.join(","))],
+        }
+    }
+}
+
+pub struct Skim;
+
+impl Backend for Skim {
+    fn name(&self) -> &'static str {
+        "sk"
+    }
+
+    fn command(&self, query: &str) -> Command {
+        let mut command = Command::new("sk");
+        command.args(["--query", query]);
+        command
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        // same codes as fzf
+        matches!(code, Some(1 | 130))
+    }
+
+    fn preview(&self, command: &str) -> Vec<String> {
+        // same options as fzf
+        Fzf.preview(command)
+    }
+
+    fn hide_path(&self) -> Vec<String> {
+        Fzf.hide_path()
+    }
+
+    fn multi(&self) -> Option<Vec<String>> {
+        Fzf.multi()
+    }
+
+    fn expect(&self, keys: &[String]) -> Vec<String> {
+        Fzf.expect(keys)
+    }
+}
+
+pub struct Fzy;
+
+impl Backend for Fzy {
+    fn name(&self) -> &'static str {
+        "fzy"
+    }
+
+    fn command(&self, query: &str) -> Command {
+        let mut command = Command::new("fzy");
+        command.args(["--query", query]);
+        command
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        matches!(code, Some(1))
+    }
+}
+
+pub struct Peco;
+
+impl Backend for Peco {
+    fn name(&self) -> &'static str {
+        "peco"
+    }
+
+    fn command(&self, query: &str) -> Command {
+        let mut command = Command::new("peco");
+        command.args(["--query", query]);
+        command
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        matches!(code, Some(1))
+    }
+
+    fn multi(&self) -> Option<Vec<String>> {
+        // Ctrl-Space marks lines already
+        Some(Vec::new())
+    }
+}
+
+pub struct Rofi;
+
+impl Backend for Rofi {
+    fn name(&self) -> &'static str {
+        "rofi"
+    }
+
+    fn command(&self, query: &str) -> Command {
+        let mut command = Command::new("rofi");
+        command.args(["-dmenu", "-i", "-p", "sgr", "-filter", query]);
+        command
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        matches!(code, Some(1))
+    }
+
+    fn multi(&self) -> Option<Vec<String>> {
+        Some(vec!["-multi-select".to_string()])
+    }
+}
+
+pub struct Dmenu;
+
+impl Backend for Dmenu {
+    fn name(&self) -> &'static str {
+        "dmenu"
+    }
+
+    fn command(&self, _query: &str) -> Command {
+        // dmenu cannot start out filtered
+        let mut command = Command::new("dmenu");
+        command.args(["-i", "-l", "20", "-p", "sgr"]);
+        command
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        matches!(code, Some(1))
+    }
+}
+
+/// any command line that reads lines from stdin and prints the chosen one,
+/// run through the shell so it can have quotes and pipes. `{query}` in it
+/// is replaced with the query, quoted for that shell. It has its options in
+/// it already, so `selector-options` are ignored
+#[derive(Clone)]
+pub struct Custom {
+    pub command: String,
+}
+
+impl Backend for Custom {
+    fn name(&self) -> &'static str {
+        "selector-command"
+    }
+
+    fn command(&self, query: &str) -> Command {
+        shell_command(&self.command.replace("{query}", &shell_quote(query)))
+    }
+
+    fn is_cancelled(&self, code: Option<i32>) -> bool {
+        // there is no telling what its codes mean, except for the shell's
+        // own for a missing program
+        !matches!(code, Some(126 | 127 | 9009) | None)
+    }
+
+    fn takes_options(&self) -> bool {
+        false
+    }
+
+    fn multi(&self) -> Option<Vec<String>> {
+        // whatever it prints is taken as is
+        Some(Vec::new())
+    }
+}
@@ -1,4 +1,47 @@
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Lines of `reader` without their line endings. A line that is not UTF-8 is
+/// skipped instead of ending the rest of the file, but the first failed read
+/// does, since reading again would only fail again.
+pub fn read_lines<R: BufRead>(reader: R) -> impl Iterator<Item = String> {
+    reader
+        .split(b'\n')
+        .map_while(Result::ok)
+        .filter_map(|line| String::from_utf8(line).ok())
+        .map(|mut line| {
+            if line.ends_with('\r') {
+                line.pop();
+            }
+            line
+        })
+}
+
+/// `line` as the system's shell runs it: `sh -c`, or `cmd /C` on Windows
+pub fn shell_command(line: &str) -> Command {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+
+    let mut command = Command::new(shell);
+    command.args([flag, line]);
+    command
+}
+
+/// `s` as one word for `shell_command`
+pub fn shell_quote(s: &str) -> String {
+    #[cfg(windows)]
+    return format!("\"{}\"", s.replace('"', ""));
+    #[cfg(not(windows))]
+    return posix_quote(s);
+}
+
+/// `s` as one word for sh, bash or zsh
+pub fn posix_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
 
 pub trait PathExt {
     fn must_be_dir(&self) -> Result<PathBuf, String>;
@@ -138,13 +181,15 @@ impl StringExt for String {
 }
 
 pub enum LogLevel {
-    _Info,
+    Info,
     Error,
     Warn,
 }
 
-pub fn logln(level: LogLevel, msg: String) {
-    println!("{} {}", log_header(level), msg);
+/// stdout is only for what a command outputs, like the path `run` prints, so
+/// messages go to stderr
+pub fn elogln(level: LogLevel, msg: String) {
+    eprintln!("{} {}", log_header(level), msg);
 }
 
 pub fn format_log(level: LogLevel, msg: String) -> String {
@@ -153,7 +198,7 @@ pub fn format_log(level: LogLevel, msg: String) -> String {
 
 pub fn log_header(level: LogLevel) -> &'static str {
     match level {
-        LogLevel::_Info => info_header(),
+        LogLevel::Info => info_header(),
         LogLevel::Error => error_header(),
         LogLevel::Warn => warn_header(),
     }
@@ -170,3 +215,55 @@ fn error_header() -> &'static str {
 fn warn_header() -> &'static str {
     "\x1b[33m[WARN]\x1b[0m"
 }
+
+/// what sgr exits with, so scripts can tell what happened without reading
+/// what it printed
+#[derive(Clone, Copy)]
+pub enum Exit {
+    /// anything not covered below
+    Error = 1,
+    /// bad flags, config.txt or dirs.txt
+    Config = 2,
+    /// the picker was closed without choosing a repo
+    Cancelled = 3,
+    /// no repos were found, or none matched the keywords of `query`
+    NoRepos = 4,
+    /// the selector is not installed
+    SelectorMissing = 5,
+}
+
+/// an error message along with what sgr exits with because of it; the
+/// message is empty if there is nothing more to say than the exit code
+pub struct Failure {
+    pub exit: Exit,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(exit: Exit, message: String) -> Failure {
+        Failure { exit, message }
+    }
+}
+
+/// errors are plain errors unless said otherwise
+impl From<String> for Failure {
+    fn from(message: String) -> Failure {
+        Failure::new(Exit::Error, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn quoted_words_reach_the_shell_unchanged() {
+        for word in ["plain", "with space", "it's", "'\\''", "$HOME `id` \"x\""] {
+            let output = shell_command(&format!("printf %s {}", shell_quote(word)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), word);
+        }
+    }
+}
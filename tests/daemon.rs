@@ -0,0 +1,210 @@
+#![cfg(target_os = "linux")]
+
+// standard library
+use std::fs;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// third party
+use tempfile::TempDir;
+
+/// a daemon of its own, with its config dir and repos in a temp dir
+struct Daemon {
+    dir: TempDir,
+}
+
+impl Daemon {
+    /// a config dir with one root in dirs.txt, which holds the repo `a`
+    fn new() -> Daemon {
+        let dir = tempfile::tempdir().unwrap();
+        let daemon = Daemon { dir };
+        fs::create_dir(daemon.config()).unwrap();
+        fs::create_dir(daemon.repos()).unwrap();
+        daemon.add_repo("a");
+        fs::write(
+            daemon.config().join("dirs.txt"),
+            format!("{}\n", daemon.repos().display()),
+        )
+        .unwrap();
+        daemon
+    }
+
+    fn config(&self) -> PathBuf {
+        self.dir.path().join("config")
+    }
+
+    fn repos(&self) -> PathBuf {
+        self.dir.path().join("repos")
+    }
+
+    fn add_repo(&self, name: &str) {
+        let dot_git = self.repos().join(name).join(".git");
+        fs::create_dir_all(&dot_git).unwrap();
+        fs::write(dot_git.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    }
+
+    fn sgr(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_sugar"))
+            .args(args)
+            .env("SGR_CONFIG_DIR", self.config())
+            .output()
+            .unwrap()
+    }
+
+    fn start(&self) {
+        let output = self.sgr(&["daemon", "start"]);
+        assert!(output.status.success(), "{:?}", output);
+        self.wait_for(|status| status.contains("repos"));
+    }
+
+    /// what `daemon status` prints, or `None` if it is not running
+    fn status(&self) -> Option<String> {
+        let output = self.sgr(&["daemon", "status"]);
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// waits until `daemon status` says what `done` looks for
+    fn wait_for<F: Fn(&str) -> bool>(&self, done: F) -> String {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let status = self.status();
+            match status {
+                Some(status) if done(&status) => return status,
+                _ if Instant::now() > deadline => panic!("daemon status: {:?}", status),
+                _ => sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        _ = self.sgr(&["daemon", "stop"]);
+    }
+}
+
+fn socket(daemon: &Daemon) -> PathBuf {
+    daemon.config().join("daemon.sock")
+}
+
+fn has_line(status: &str, line: &str) -> bool {
+    status.lines().any(|l| l == line)
+}
+
+#[test]
+fn starts_and_stops() {
+    let daemon = Daemon::new();
+    daemon.start();
+    let status = daemon.status().unwrap();
+    assert!(has_line(&status, "roots 1"), "{}", status);
+    assert!(has_line(&status, "repos 1"), "{}", status);
+    assert!(daemon.config().join("daemon.log").exists());
+
+    assert!(daemon.sgr(&["daemon", "stop"]).status.success());
+    assert!(!socket(&daemon).exists());
+    assert!(daemon.status().is_none());
+}
+
+#[test]
+fn refuses_to_start_on_a_broken_config() {
+    let daemon = Daemon::new();
+    fs::write(daemon.config().join("config.txt"), "bogus\n").unwrap();
+    let output = daemon.sgr(&["daemon", "start"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!socket(&daemon).exists());
+}
+
+#[test]
+fn answers_queries() {
+    let daemon = Daemon::new();
+    daemon.start();
+    let output = daemon.sgr(&["query", "a", "--format", "tsv"]);
+    assert!(output.status.success(), "{:?}", output);
+    let expected = format!(
+        "{}\t{}\t1\tgit\tclone\n",
+        daemon.repos().join("a").display(),
+        daemon.repos().display()
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn picks_up_new_repos() {
+    let daemon = Daemon::new();
+    daemon.start();
+    daemon.add_repo("b");
+    daemon.wait_for(|status| has_line(status, "repos 2"));
+}
+
+#[test]
+fn rescans_after_an_ignore_file_is_edited() {
+    let daemon = Daemon::new();
+    let sgrignore = daemon.repos().join(".sgrignore");
+    fs::write(&sgrignore, "b\n").unwrap();
+    daemon.start();
+    // written over in place, which leaves the root directory as it was
+    fs::write(&sgrignore, "a\n").unwrap();
+    daemon.wait_for(|status| has_line(status, "repos 0"));
+}
+
+#[test]
+fn reloads_dirs_txt() {
+    let daemon = Daemon::new();
+    let other = daemon.dir.path().join("other");
+    fs::create_dir_all(other.join("c").join(".git")).unwrap();
+    fs::write(other.join("c").join(".git").join("HEAD"), "").unwrap();
+    daemon.start();
+
+    let dirs_txt = format!("{}\n{}\n", daemon.repos().display(), other.display());
+    fs::write(daemon.config().join("dirs.txt"), dirs_txt).unwrap();
+    let status = daemon.wait_for(|status| has_line(status, "roots 2"));
+    assert!(has_line(&status, "repos 2"), "{}", status);
+}
+
+#[test]
+fn keeps_the_previous_config_when_it_breaks() {
+    let daemon = Daemon::new();
+    daemon.start();
+    let config_txt = daemon.config().join("config.txt");
+    fs::write(&config_txt, "bogus\n").unwrap();
+    let status = daemon.wait_for(|status| status.contains("kept the previous config"));
+    assert!(has_line(&status, "repos 1"), "{}", status);
+
+    fs::write(&config_txt, "depth = 3\n").unwrap();
+    daemon.wait_for(|status| !status.contains("kept the previous config"));
+}
+
+#[test]
+fn a_silent_client_does_not_block_others() {
+    let daemon = Daemon::new();
+    daemon.start();
+    let _silent = UnixStream::connect(socket(&daemon)).unwrap();
+    let started = Instant::now();
+    assert!(daemon.status().is_some());
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn a_daemon_that_never_answers_is_searched_around() {
+    let daemon = Daemon::new();
+    // accepts connections, as the kernel does for a listener, but never
+    // reads or writes, like a daemon still on its first search
+    let _wedged = UnixListener::bind(socket(&daemon)).unwrap();
+
+    let started = Instant::now();
+    let output = daemon.sgr(&["query", "a"]);
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(output.status.success(), "{:?}", output);
+    let expected = format!("{}\n", daemon.repos().join("a").display());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    let output = daemon.sgr(&["daemon", "status"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not answering"));
+}
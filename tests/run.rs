@@ -0,0 +1,66 @@
+// standard library
+use std::fs;
+use std::process::{Command, Output};
+
+// third party
+use tempfile::TempDir;
+
+/// a config dir whose dirs.txt has the empty directory `repos` in it, and
+/// whose selector prints whatever it is given
+fn config() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let repos = dir.path().join("repos");
+    fs::create_dir(&repos).unwrap();
+    fs::write(
+        dir.path().join("dirs.txt"),
+        format!("{}\n", repos.display()),
+    )
+    .unwrap();
+    fs::write(dir.path().join("config.txt"), "selector-command = cat\n").unwrap();
+    dir
+}
+
+fn sgr(config: &TempDir, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sugar"))
+        .args(args)
+        .env("SGR_CONFIG_DIR", config.path())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn nothing_found_is_no_repos() {
+    let config = config();
+    let output = sgr(&config, &[]);
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+}
+
+#[test]
+fn nothing_found_in_time_is_no_repos() {
+    let config = config();
+    // history is ranked before the search starts, which keeps it going well
+    // after the picker gave up on it
+    let history: String = (0..100_000)
+        .map(|i| format!("1\t0\t{}\n", config.path().join(i.to_string()).display()))
+        .collect();
+    fs::write(config.path().join("history.txt"), history).unwrap();
+    for _ in 0..5 {
+        let output = sgr(&config, &["--timeout", "0"]);
+        assert_eq!(output.status.code(), Some(4), "{:?}", output);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("timed out"));
+    }
+}
+
+#[test]
+fn found_in_time_is_picked() {
+    let config = config();
+    let repo = config.path().join("repos").join("a");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::write(repo.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    let output = sgr(&config, &["--timeout", "10"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n", repo.display())
+    );
+}